@@ -0,0 +1,44 @@
+//! Timing harness shared by the benchmarks
+
+use grib2_ffi::*;
+use std::ffi::CString;
+use std::time::{Duration, Instant};
+
+const ROUNDS: u32 = 3;
+
+/// File named by GRIB2_BENCH_FILE, or the bundled GFS sample
+pub fn bench_file() -> CString {
+    let path = std::env::var("GRIB2_BENCH_FILE").unwrap_or_else(|_| {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../examples/gfs_sample.grib2").to_string()
+    });
+    println!("{path}");
+    CString::new(path).unwrap()
+}
+
+fn drain(reader: *mut Grib2Reader) -> usize {
+    let mut total = 0;
+    loop {
+        let batch = grib2_read_batch(reader, 65536);
+        total += batch.count;
+        let has_more = batch.has_more;
+        grib2_free_batch(batch);
+        if !has_more {
+            return total;
+        }
+    }
+}
+
+/// Best of a few rounds of opening a reader and streaming every point
+pub fn time(label: &str, open: impl Fn() -> *mut Grib2Reader) {
+    let mut best = Duration::MAX;
+    let mut points = 0;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        let reader = open();
+        assert!(!reader.is_null(), "failed to open benchmark file");
+        points = drain(reader);
+        grib2_close(reader);
+        best = best.min(start.elapsed());
+    }
+    println!("{label:<12} {points:>12} points  {best:?}");
+}
@@ -0,0 +1,23 @@
+//! Compare buffered file reads with grib2_open_mmap
+//!
+//! Run with a multi-message file, e.g. a full HRRR or GFS forecast hour:
+//! GRIB2_BENCH_FILE=/tmp/hrrr.t00z.wrfsfcf00.grib2 cargo bench --features mmap --bench mmap_open
+
+mod common;
+
+use common::{bench_file, time};
+use grib2_ffi::*;
+use std::ptr;
+
+fn main() {
+    let c_path = bench_file();
+
+    time("buffered", || {
+        let mut error = ptr::null_mut();
+        grib2_open_with_error(c_path.as_ptr(), &mut error, ptr::null_mut())
+    });
+    time("mmap", || {
+        let mut error = ptr::null_mut();
+        grib2_open_mmap(c_path.as_ptr(), &mut error, ptr::null_mut())
+    });
+}
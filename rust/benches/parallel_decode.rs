@@ -0,0 +1,23 @@
+//! Compare sequential streaming with grib2_open_parallel
+//!
+//! Run with a multi-message file, e.g. a full GFS 0.25° forecast hour:
+//! GRIB2_BENCH_FILE=/tmp/gfs.t00z.pgrb2.0p25.f000 cargo bench --features parallel
+
+mod common;
+
+use common::{bench_file, time};
+use grib2_ffi::*;
+use std::ptr;
+
+fn main() {
+    let c_path = bench_file();
+
+    time("sequential", || {
+        let mut error = ptr::null_mut();
+        grib2_open_with_error(c_path.as_ptr(), &mut error, ptr::null_mut())
+    });
+    time("parallel", || {
+        let mut error = ptr::null_mut();
+        grib2_open_parallel(c_path.as_ptr(), &mut error, ptr::null_mut())
+    });
+}
@@ -0,0 +1,23 @@
+//! Feed arbitrary bytes to Grib2Reader::from_bytes and stream everything it
+//! accepts; any panic, abort or sanitizer report is a bug
+//!
+//! Seed the corpus with the test files and run from rust/:
+//! mkdir -p fuzz/corpus/from_bytes && cp ../test/data/*.grib2 fuzz/corpus/from_bytes/
+//! cargo +nightly fuzz run from_bytes -- -max_len=65536
+
+#![no_main]
+
+use grib2_ffi::Grib2Reader;
+use libfuzzer_sys::fuzz_target;
+
+// A few bytes can describe a consistent but enormous grid; streaming all of it
+// only finds timeouts, not crashes
+const MAX_POINTS: usize = 1 << 20;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(mut reader) = Grib2Reader::from_bytes(data) else {
+        return;
+    };
+    for _ in reader.messages() {}
+    for _ in reader.points().take(MAX_POINTS) {}
+});
@@ -0,0 +1,67 @@
+//! Export of decoded messages through the Arrow C Data Interface
+//!
+//! A message becomes one struct array whose children are the columns of a
+//! `RecordBatch`, so consumers such as DuckDB can import it without walking
+//! `Grib2DataPoint` rows. Metadata columns repeat the message's value per row.
+
+use super::ParsedMessage;
+use arrow_array::{
+    ArrayRef, Float64Array, Int64Array, RecordBatch, StructArray, UInt16Array, UInt32Array,
+    UInt8Array,
+};
+use arrow_data::ffi::FFI_ArrowArray;
+use arrow_schema::ffi::FFI_ArrowSchema;
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use std::sync::Arc;
+
+/// Build the record batch for one message's (lat, lon, value) points
+pub(crate) fn record_batch(
+    msg: &ParsedMessage,
+    points: &[(f64, f64, f64)],
+) -> Result<RecordBatch, ArrowError> {
+    let n = points.len();
+    let schema = Schema::new(vec![
+        Field::new("latitude", DataType::Float64, false),
+        Field::new("longitude", DataType::Float64, false),
+        Field::new("value", DataType::Float64, false),
+        Field::new("discipline", DataType::UInt8, false),
+        Field::new("parameter_category", DataType::UInt8, false),
+        Field::new("parameter_number", DataType::UInt8, false),
+        Field::new("reference_time", DataType::Int64, false),
+        Field::new("forecast_time", DataType::Int64, false),
+        Field::new("valid_time", DataType::Int64, false),
+        Field::new("surface_type", DataType::UInt8, false),
+        Field::new("surface_value", DataType::Float64, false),
+        Field::new("message_index", DataType::UInt32, false),
+        Field::new("submessage_index", DataType::UInt32, false),
+        Field::new("source_index", DataType::UInt32, false),
+        Field::new("centre", DataType::UInt16, false),
+        Field::new("kind", DataType::UInt8, false),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(Float64Array::from_iter_values(points.iter().map(|p| p.0))),
+        Arc::new(Float64Array::from_iter_values(points.iter().map(|p| p.1))),
+        Arc::new(Float64Array::from_iter_values(points.iter().map(|p| p.2))),
+        Arc::new(UInt8Array::from(vec![msg.discipline; n])),
+        Arc::new(UInt8Array::from(vec![msg.parameter_category; n])),
+        Arc::new(UInt8Array::from(vec![msg.parameter_number; n])),
+        Arc::new(Int64Array::from(vec![msg.reference_time; n])),
+        Arc::new(Int64Array::from(vec![msg.forecast_time; n])),
+        Arc::new(Int64Array::from(vec![msg.valid_time; n])),
+        Arc::new(UInt8Array::from(vec![msg.surface_type; n])),
+        Arc::new(Float64Array::from(vec![msg.surface_value; n])),
+        Arc::new(UInt32Array::from(vec![msg.message_index; n])),
+        Arc::new(UInt32Array::from(vec![msg.submessage_index; n])),
+        Arc::new(UInt32Array::from(vec![msg.source_index; n])),
+        Arc::new(UInt16Array::from(vec![msg.identification.centre; n])),
+        Arc::new(UInt8Array::from(vec![msg.kind as u8; n])),
+    ];
+    RecordBatch::try_new(Arc::new(schema), columns)
+}
+
+/// Move a record batch into C Data Interface structs owned by the caller
+pub(crate) fn export(batch: RecordBatch) -> Result<(FFI_ArrowArray, FFI_ArrowSchema), ArrowError> {
+    let schema = FFI_ArrowSchema::try_from(batch.schema().as_ref())?;
+    let array = FFI_ArrowArray::new(&StructArray::from(batch).into());
+    Ok((array, schema))
+}
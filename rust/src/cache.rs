@@ -0,0 +1,135 @@
+//! Process-wide LRU cache of unpacked message values
+//!
+//! Reopening a file re-reads its section headers, which is cheap, but unpacking
+//! section 7 again is not. With a capacity set, values unpacked from a file are
+//! kept in native scan order, keyed by the file's identity and the message's
+//! position in it, so later readers over the same content skip the unpacking.
+//! Off (capacity 0) by default.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
+
+/// Identity of a file's content: path, size and modification time
+/// A file rewritten in place with the same size within the mtime resolution
+/// looks unchanged
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ContentKey {
+    path: PathBuf,
+    len: u64,
+    modified: SystemTime,
+}
+
+impl ContentKey {
+    /// None when the file's metadata can't be read, which leaves it uncached
+    pub(crate) fn of_file(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(ContentKey {
+            path: PathBuf::from(path),
+            len: metadata.len(),
+            modified: metadata.modified().ok()?,
+        })
+    }
+}
+
+/// A file's content and the position of a submessage in it
+pub(crate) type MessageKey = (ContentKey, usize);
+
+struct Entry {
+    values: Arc<[f32]>,
+    last_used: u64,
+}
+
+struct DecodeCache {
+    capacity: usize, // bytes of values kept, 0 when caching is off
+    used: usize,
+    tick: u64,
+    entries: HashMap<MessageKey, Entry>,
+}
+
+static CACHE: Mutex<Option<DecodeCache>> = Mutex::new(None);
+
+fn cache() -> MutexGuard<'static, Option<DecodeCache>> {
+    // Entries are inserted and removed whole, so a panic elsewhere leaves it usable
+    CACHE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn size_of(values: &[f32]) -> usize {
+    std::mem::size_of_val(values)
+}
+
+/// Keep up to `bytes` of values, evicting the least recently used; 0 turns the
+/// cache off and drops everything in it
+pub(crate) fn set_capacity(bytes: usize) {
+    let mut cache = cache();
+    if bytes == 0 {
+        *cache = None;
+        return;
+    }
+    let cache = cache.get_or_insert_with(|| DecodeCache {
+        capacity: 0,
+        used: 0,
+        tick: 0,
+        entries: HashMap::new(),
+    });
+    cache.capacity = bytes;
+    cache.evict();
+}
+
+/// Whether values are being cached at all
+pub(crate) fn enabled() -> bool {
+    cache().is_some()
+}
+
+pub(crate) fn get(key: &MessageKey) -> Option<Arc<[f32]>> {
+    let mut guard = cache();
+    let cache = guard.as_mut()?;
+    cache.tick += 1;
+    let tick = cache.tick;
+    let entry = cache.entries.get_mut(key)?;
+    entry.last_used = tick;
+    Some(entry.values.clone())
+}
+
+/// Values larger than the whole capacity are not kept
+pub(crate) fn insert(key: MessageKey, values: Arc<[f32]>) {
+    let mut guard = cache();
+    let Some(cache) = guard.as_mut() else {
+        return;
+    };
+    let size = size_of(&values);
+    if size > cache.capacity {
+        return;
+    }
+    cache.tick += 1;
+    let entry = Entry {
+        values,
+        last_used: cache.tick,
+    };
+    if let Some(old) = cache.entries.insert(key, entry) {
+        cache.used -= size_of(&old.values);
+    }
+    cache.used += size;
+    cache.evict();
+}
+
+impl DecodeCache {
+    /// Drop least recently used entries until the rest fits the capacity
+    fn evict(&mut self) {
+        while self.used > self.capacity {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.used -= size_of(&entry.values);
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Complex packing (data representation templates 5.2 and 5.3)
+//!
+//! The grib crate only unpacks second-order spatial differencing without missing
+//! value management, and slices section 7 at offsets taken from the headers
+//! without checking them, so a corrupt message panics inside it. This unpacks
+//! every variant with checked reads: template 5.2, first- and second-order
+//! differencing, and primary/secondary missing values coded inside the groups,
+//! following WMO Manual on Codes FM 92 GRIB2.
+
+/// Section 5 and 7 payloads (see SharedSource::read_payload) of a complex-packed
+/// message, plus its bitmap
+pub(crate) struct ComplexPacked {
+    pub(crate) repr: Vec<u8>,
+    pub(crate) bitmap: Option<Vec<u8>>, // section 6 bits after the indicator, if present
+    pub(crate) data: Vec<u8>,
+    pub(crate) num_points: usize, // from section 3, bitmapped points included
+}
+
+/// Reads bit fields most significant bit first
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize, // in bits
+}
+
+impl Bits<'_> {
+    fn read(&mut self, width: u32) -> Result<u32, String> {
+        if width > 32 {
+            return Err(format!("{width}-bit field is wider than 32 bits"));
+        }
+        let end = self.pos + width as usize;
+        if end > self.data.len() * 8 {
+            return Err("Section 7 ends before the packed values".into());
+        }
+        // Up to a byte at a time
+        let mut value = 0u64;
+        while self.pos < end {
+            let offset = self.pos % 8;
+            let take = (8 - offset).min(end - self.pos);
+            let byte = u64::from(self.data[self.pos / 8]);
+            value = (value << take) | (byte >> (8 - offset - take)) & ((1 << take) - 1);
+            self.pos += take;
+        }
+        Ok(value as u32)
+    }
+
+    /// Skip to the next octet boundary, as between the group descriptor blocks
+    fn align(&mut self) {
+        self.pos = self.pos.div_ceil(8) * 8;
+    }
+}
+
+/// Sign and magnitude integer of `bytes` octets
+fn signed(bytes: &[u8]) -> i64 {
+    let magnitude = bytes.iter().fold(0i64, |acc, &b| acc << 8 | i64::from(b));
+    let sign_bit = 1i64 << (bytes.len() * 8 - 1);
+    if magnitude & sign_bit != 0 {
+        -(magnitude & !sign_bit)
+    } else {
+        magnitude
+    }
+}
+
+/// What a group value stands for once missing value management is applied
+#[derive(Clone, Copy)]
+enum Packed {
+    Value(i64),
+    Missing,
+}
+
+impl ComplexPacked {
+    /// Unpacked values for every grid point, NaN where the bitmap or the
+    /// missing value management marks a point as missing
+    pub(crate) fn unpack(&self) -> Result<Vec<f32>, String> {
+        let repr = &self.repr;
+        let octets = |from: usize, to: usize| {
+            repr.get(from - 6..to - 5)
+                .ok_or_else(|| format!("Section 5 ends before octet {to}"))
+        };
+        let u32_at =
+            |n: usize| octets(n, n + 3).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+        let u8_at = |n: usize| octets(n, n).map(|b| b[0]);
+
+        let template = octets(10, 11).map(|b| u16::from_be_bytes([b[0], b[1]]))?;
+        let encoded = u32_at(6)? as usize;
+        let reference = f32::from_bits(u32_at(12)?);
+        let binary_scale = signed(octets(16, 17)?);
+        let decimal_scale = signed(octets(18, 19)?);
+        let ref_bits = u32::from(u8_at(20)?);
+        if u8_at(22)? != 1 {
+            return Err("Only general group splitting (code table 5.4 value 1) is defined".into());
+        }
+        let missing_management = u8_at(23)?;
+        let groups = u32_at(32)? as usize;
+        let width_ref = u32::from(u8_at(36)?);
+        let width_bits = u32::from(u8_at(37)?);
+        let length_ref = u32_at(38)?;
+        let length_increment = u32::from(u8_at(42)?);
+        let last_length = u32_at(43)?;
+        let length_bits = u32::from(u8_at(47)?);
+        let (order, descriptor_octets) = if template == 3 {
+            (u8_at(48)? as usize, u8_at(49)? as usize)
+        } else {
+            (0, 0)
+        };
+        if order > 2 || (order > 0 && !(1..=4).contains(&descriptor_octets)) {
+            return Err(format!(
+                "Unsupported spatial differencing of order {order} with {descriptor_octets}-octet descriptors"
+            ));
+        }
+        if missing_management > 2 {
+            return Err(format!(
+                "Unknown missing value management {missing_management}"
+            ));
+        }
+
+        // Spatial differencing descriptors: the first values, then the overall minimum
+        let descriptors: Vec<i64> = (0..if order > 0 { order + 1 } else { 0 })
+            .map(|k| {
+                self.data
+                    .get(k * descriptor_octets..(k + 1) * descriptor_octets)
+                    .map(signed)
+                    .ok_or("Section 7 ends inside the spatial differencing descriptors")
+            })
+            .collect::<Result<_, _>>()?;
+
+        let mut bits = Bits {
+            data: &self.data,
+            pos: (order + usize::from(order > 0)) * descriptor_octets * 8,
+        };
+        let mut read_block = |count: usize, width: u32| -> Result<Vec<u32>, String> {
+            let block = (0..count).map(|_| bits.read(width)).collect();
+            bits.align();
+            block
+        };
+        let refs = read_block(groups, ref_bits)?;
+        let widths = read_block(groups, width_bits)?;
+        let lengths = read_block(groups, length_bits)?;
+
+        // Group values, with the two all-ones codes standing for missing values
+        let all_ones = |width: u32| (1u64 << width) - 1;
+        if encoded > self.num_points {
+            return Err(format!(
+                "{encoded} packed values for {} grid points",
+                self.num_points
+            ));
+        }
+        // Grown as groups are read: `encoded` comes from the header and is only
+        // bounded by the grid size, not by what section 7 holds
+        let mut packed = Vec::new();
+        for g in 0..groups {
+            let width = width_ref
+                .checked_add(widths[g])
+                .ok_or_else(|| format!("Group {g} width overflows"))?;
+            let length = if g + 1 == groups {
+                u64::from(last_length)
+            } else {
+                u64::from(length_ref) + u64::from(length_increment) * u64::from(lengths[g])
+            } as usize;
+            if length > encoded - packed.len() {
+                return Err(format!("Group lengths exceed the {encoded} packed values"));
+            }
+            let missing = |value: u64, width: u32| {
+                (missing_management >= 1 && value == all_ones(width))
+                    || (missing_management == 2 && value == all_ones(width) - 1)
+            };
+            if width == 0 {
+                // A constant group: no values follow, the reference may itself be missing
+                let value = if ref_bits > 0 && missing(u64::from(refs[g]), ref_bits) {
+                    Packed::Missing
+                } else {
+                    Packed::Value(i64::from(refs[g]))
+                };
+                packed.extend(std::iter::repeat_n(value, length));
+                continue;
+            }
+            for _ in 0..length {
+                let value = bits.read(width)?;
+                packed.push(if missing(u64::from(value), width) {
+                    Packed::Missing
+                } else {
+                    Packed::Value(i64::from(refs[g]) + i64::from(value))
+                });
+            }
+        }
+
+        // Undo the differencing over the present values only, skipping missing ones
+        if order > 0 {
+            let minimum = descriptors[order];
+            let mut history = [0i64; 2];
+            let present = packed.iter_mut().filter_map(|p| match p {
+                Packed::Value(v) => Some(v),
+                Packed::Missing => None,
+            });
+            for (k, value) in present.enumerate() {
+                *value = if k < order {
+                    Some(descriptors[k])
+                } else if order == 1 {
+                    value
+                        .checked_add(minimum)
+                        .and_then(|v| v.checked_add(history[1]))
+                } else {
+                    value
+                        .checked_add(minimum)
+                        .and_then(|v| v.checked_add(history[1].checked_mul(2)?))
+                        .and_then(|v| v.checked_sub(history[0]))
+                }
+                .ok_or("Spatial differencing overflows 64-bit values")?;
+                history = [history[1], *value];
+            }
+        }
+
+        let scale = 2f64.powi(binary_scale as i32);
+        let divisor = 10f64.powi(decimal_scale as i32);
+        let mut values = packed.into_iter().map(|p| match p {
+            Packed::Value(v) => ((f64::from(reference) + v as f64 * scale) / divisor) as f32,
+            Packed::Missing => f32::NAN,
+        });
+
+        let unpacked: Vec<f32> = match &self.bitmap {
+            None => values.take(self.num_points).collect(),
+            Some(bitmap) => (0..self.num_points)
+                .map(|k| {
+                    let set = bitmap
+                        .get(k / 8)
+                        .is_some_and(|b| b & (0x80 >> (k % 8)) != 0);
+                    if set {
+                        values.next().unwrap_or(f32::NAN)
+                    } else {
+                        f32::NAN
+                    }
+                })
+                .collect(),
+        };
+        if unpacked.len() != self.num_points {
+            return Err(format!(
+                "Unpacked {} values for {} grid points",
+                unpacked.len(),
+                self.num_points
+            ));
+        }
+        Ok(unpacked)
+    }
+}
@@ -0,0 +1,246 @@
+//! Encoding of regular lat/lon grids as GRIB2 messages
+//!
+//! Writes the smallest message decoders accept: grid template 3.0, product
+//! template 4.0 and simple packing (template 5.0), with a section 6 bitmap when
+//! some values are NaN. Enough for test fixtures and exporting derived fields.
+
+use super::{civil_time, Grib2DataPoint};
+
+/// Bits per packed value, the precision of the f32 values the reader decodes to
+const BITS_PER_VALUE: u32 = 24;
+
+/// Encode points laid out row by row (ni columns, nj rows) as one message
+/// Time and level come from the first point; forecast_time is written in hours
+pub(crate) fn regular_latlon(
+    points: &[Grib2DataPoint],
+    ni: usize,
+    nj: usize,
+    (discipline, category, number): (u8, u8, u8),
+) -> Result<Vec<u8>, String> {
+    if ni == 0 || nj == 0 || ni.checked_mul(nj) != Some(points.len()) {
+        return Err(format!(
+            "Expected ni * nj = {ni} * {nj} points, got {}",
+            points.len()
+        ));
+    }
+    let first = &points[0];
+    let forecast_time = u32::try_from(first.forecast_time)
+        .map_err(|_| format!("Forecast time {} is out of range", first.forecast_time))?;
+    let values: Vec<f64> = points.iter().map(|p| p.value).collect();
+    let (repr, bitmap, data) = pack(&values);
+
+    let mut body = Vec::new();
+    body.extend(section(1, &identification(first.reference_time)));
+    body.extend(section(3, &grid(points, ni, nj)));
+    body.extend(section(4, &product(first, category, number, forecast_time)));
+    body.extend(section(5, &repr));
+    body.extend(section(6, &bitmap));
+    body.extend(section(7, &data));
+
+    // Section 0 carries the total length, so it goes in front last
+    let total = 16 + body.len() + 4;
+    let mut message = Vec::with_capacity(total);
+    message.extend(b"GRIB");
+    message.extend([0, 0, discipline, 2]);
+    message.extend((total as u64).to_be_bytes());
+    message.extend(body);
+    message.extend(b"7777");
+    Ok(message)
+}
+
+/// Prefix a section body with its length and number
+fn section(number: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(5 + body.len());
+    out.extend((5 + body.len() as u32).to_be_bytes());
+    out.push(number);
+    out.extend(body);
+    out
+}
+
+/// Sign-magnitude encoding used by GRIB2 for signed integers
+fn signed32(value: i32) -> [u8; 4] {
+    let magnitude = value.unsigned_abs() & 0x7fff_ffff;
+    let sign = if value < 0 { 0x8000_0000 } else { 0 };
+    (magnitude | sign).to_be_bytes()
+}
+
+fn signed16(value: i32) -> [u8; 2] {
+    let magnitude = value.unsigned_abs().min(0x7fff) as u16;
+    let sign = if value < 0 { 0x8000 } else { 0 };
+    (magnitude | sign).to_be_bytes()
+}
+
+/// Degrees as the micro-degree integers of template 3.0
+fn micro_degrees(degrees: f64) -> i32 {
+    (degrees * 1e6).round() as i32
+}
+
+/// Section 1: missing centre, reference time as the start of the forecast
+fn identification(reference_time: i64) -> Vec<u8> {
+    let (year, month, day, hour, minute, second) = civil_time(reference_time);
+    let mut body = Vec::with_capacity(16);
+    body.extend(65535u16.to_be_bytes()); // centre
+    body.extend(65535u16.to_be_bytes()); // subcentre
+    body.extend([2, 0, 1]); // master/local table versions, significance
+    body.extend(year.to_be_bytes());
+    body.extend([month, day, hour, minute, second]);
+    body.extend([0, 1]); // operational products, forecast
+    body
+}
+
+/// Section 3 with template 3.0; the scanning direction follows the point order
+fn grid(points: &[Grib2DataPoint], ni: usize, nj: usize) -> Vec<u8> {
+    let first = &points[0];
+    let last = &points[points.len() - 1];
+
+    let westward =
+        ni > 1 && (points[1].longitude - first.longitude + 180.0).rem_euclid(360.0) < 180.0;
+    let northward = nj > 1 && points[ni].latitude > first.latitude;
+    let lon_span = if westward {
+        (first.longitude - last.longitude).rem_euclid(360.0)
+    } else {
+        (last.longitude - first.longitude).rem_euclid(360.0)
+    };
+    let increment = |span: f64, n: usize| {
+        if n > 1 {
+            micro_degrees(span / (n - 1) as f64) as u32
+        } else {
+            u32::MAX
+        }
+    };
+    let scan_mode = if westward { 0x80 } else { 0 } | if northward { 0x40 } else { 0 };
+
+    let mut body = Vec::with_capacity(67);
+    body.push(0); // grid from a template
+    body.extend((points.len() as u32).to_be_bytes());
+    body.extend([0, 0]); // no optional list of points
+    body.extend(0u16.to_be_bytes()); // template 3.0
+    body.extend([6, 0, 0, 0, 0, 0]); // spherical earth, radius 6371229 m
+    body.extend([0; 10]); // oblate spheroid axes, unused
+    body.extend((ni as u32).to_be_bytes());
+    body.extend((nj as u32).to_be_bytes());
+    body.extend(0u32.to_be_bytes()); // basic angle: micro-degrees
+    body.extend(u32::MAX.to_be_bytes());
+    // Lo2 continues past Lo1 in the scanning direction instead of wrapping, as
+    // decoders check the pair against the scanning mode; Lo1 shifts by 360 to keep
+    // both within [-360, 360] where f32 coordinates stay precise
+    let mut first_lon = first.longitude.rem_euclid(360.0);
+    let last_lon = if westward {
+        first_lon - lon_span
+    } else {
+        if first_lon + lon_span >= 360.0 {
+            first_lon -= 360.0;
+        }
+        first_lon + lon_span
+    };
+    body.extend(signed32(micro_degrees(first.latitude)));
+    body.extend(signed32(micro_degrees(first_lon)));
+    body.push(0x30); // i and j increments given
+    body.extend(signed32(micro_degrees(last.latitude)));
+    body.extend(signed32(micro_degrees(last_lon)));
+    body.extend(increment(lon_span, ni).to_be_bytes());
+    body.extend(increment((last.latitude - first.latitude).abs(), nj).to_be_bytes());
+    body.push(scan_mode);
+    body
+}
+
+/// Section 4 with template 4.0: an instantaneous forecast at the first point's levels
+fn product(first: &Grib2DataPoint, category: u8, number: u8, forecast_time: u32) -> Vec<u8> {
+    let mut body = Vec::with_capacity(29);
+    body.extend(0u16.to_be_bytes()); // no coordinate values
+    body.extend(0u16.to_be_bytes()); // template 4.0
+    body.extend([category, number, 2, 255, 255]); // forecast, process ids missing
+    body.extend([0, 0, 0]); // no data cutoff
+    body.push(1); // hours
+    body.extend(forecast_time.to_be_bytes());
+    body.extend(fixed_surface(first.surface_type, first.surface_value));
+    body.extend(fixed_surface(first.surface_type_2, first.surface_value_2));
+    body
+}
+
+/// Surface type, scale factor and scaled value; type 255 writes all missing
+fn fixed_surface(surface_type: u8, value: f64) -> [u8; 6] {
+    if surface_type == 255 || !value.is_finite() {
+        return [surface_type, 255, 255, 255, 255, 255];
+    }
+    // Fewest decimals that represent the level exactly, as producers do
+    let scale = (0..=6)
+        .find(|&s| {
+            let scaled = value * 10f64.powi(s);
+            (scaled - scaled.round()).abs() < 1e-6 && scaled.abs() < i32::MAX as f64
+        })
+        .unwrap_or(0);
+    let scaled = signed32((value * 10f64.powi(scale)).round() as i32);
+    [
+        surface_type,
+        scale as u8,
+        scaled[0],
+        scaled[1],
+        scaled[2],
+        scaled[3],
+    ]
+}
+
+/// Simple packing of the non-NaN values; returns the section 5, 6 and 7 bodies
+/// A constant or empty field is packed with zero bits per value
+fn pack(values: &[f64]) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+    let present: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    let min = present.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = present.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let (min, max) = if present.is_empty() {
+        (0.0, 0.0)
+    } else {
+        (min, max)
+    };
+
+    // The reference value must not exceed the minimum once rounded to f32
+    let mut reference = min as f32;
+    if f64::from(reference) > min {
+        reference = reference.next_down();
+    }
+    let range = max - f64::from(reference);
+    let (bits, binary_scale) = if range > 0.0 {
+        let steps = ((1u64 << BITS_PER_VALUE) - 1) as f64;
+        (BITS_PER_VALUE, (range / steps).log2().ceil() as i32)
+    } else {
+        (0, 0)
+    };
+
+    let mut repr = Vec::with_capacity(16);
+    repr.extend((present.len() as u32).to_be_bytes());
+    repr.extend(0u16.to_be_bytes()); // template 5.0
+    repr.extend(reference.to_be_bytes());
+    repr.extend(signed16(binary_scale));
+    repr.extend(signed16(0)); // decimal scale
+    repr.extend([bits as u8, 0]); // original values were floating point
+
+    let bitmap = if present.len() == values.len() {
+        vec![255]
+    } else {
+        let mut bitmap = vec![0; 1 + values.len().div_ceil(8)];
+        for (k, _) in values.iter().enumerate().filter(|(_, v)| v.is_finite()) {
+            bitmap[1 + k / 8] |= 0x80 >> (k % 8);
+        }
+        bitmap
+    };
+
+    let mut data = Vec::with_capacity((present.len() * bits as usize).div_ceil(8));
+    if bits > 0 {
+        let max_packed = (1u64 << bits) - 1;
+        let factor = 2f64.powi(-binary_scale);
+        let (mut acc, mut filled) = (0u64, 0);
+        for value in present {
+            let packed = (((value - f64::from(reference)) * factor).round() as u64).min(max_packed);
+            acc = (acc << bits) | packed;
+            filled += bits;
+            while filled >= 8 {
+                filled -= 8;
+                data.push((acc >> filled) as u8);
+            }
+        }
+        if filled > 0 {
+            data.push((acc << (8 - filled)) as u8);
+        }
+    }
+    (repr, bitmap, data)
+}
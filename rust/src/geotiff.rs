@@ -0,0 +1,139 @@
+//! GeoTIFF output of regular lat/lon grids
+//!
+//! Writes the baseline subset every GIS reader accepts: one little-endian
+//! classic TIFF with a single uncompressed float32 band in one strip, plus the
+//! GeoTIFF model tags placing it on geographic WGS 84 coordinates. Small enough
+//! to write by hand instead of pulling in an imaging crate.
+
+use std::io::{self, Write};
+
+/// A north-up raster: rows north to south, columns west to east
+pub(crate) struct Raster<'a> {
+    pub width: usize,
+    pub height: usize,
+    pub values: &'a [f32], // row by row, NaN where missing
+    pub west: f64,         // longitude of the first column's centre
+    pub north: f64,        // latitude of the first row's centre
+    pub dx: f64,           // degrees between columns
+    pub dy: f64,           // degrees between rows, positive
+}
+
+enum Field {
+    Short(Vec<u16>),
+    Long(u32),
+    Double(Vec<f64>),
+    Ascii(&'static str),
+}
+
+impl Field {
+    /// TIFF field type and number of values
+    fn kind(&self) -> (u16, u32) {
+        match self {
+            Field::Short(v) => (3, v.len() as u32),
+            Field::Long(_) => (4, 1),
+            Field::Double(v) => (12, v.len() as u32),
+            Field::Ascii(s) => (2, s.len() as u32 + 1),
+        }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self {
+            Field::Short(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+            Field::Long(x) => x.to_le_bytes().to_vec(),
+            Field::Double(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+            Field::Ascii(s) => s.bytes().chain([0]).collect(),
+        }
+    }
+}
+
+/// Write the raster as a GeoTIFF; values are GRIB grid points, so the first
+/// pixel's outer corner lies half a step north-west of the first point
+pub(crate) fn write(mut out: impl Write, raster: &Raster) -> io::Result<()> {
+    let image_len = raster
+        .width
+        .checked_mul(raster.height)
+        .and_then(|n| n.checked_mul(4))
+        .filter(|&n| n < u32::MAX as usize / 2)
+        .ok_or_else(|| io::Error::other("Grid too large for a classic TIFF"))?;
+    let short = |v: u16| Field::Short(vec![v]);
+    let geo_keys = vec![
+        1, 1, 0, 4, // directory version, key revision 1.0, 4 keys
+        1024, 0, 1, 2, // GTModelType: geographic
+        1025, 0, 1, 1, // GTRasterType: pixel is area
+        2048, 0, 1, 4326, // GeographicType: WGS 84
+        2054, 0, 1, 9102, // GeogAngularUnits: degree
+    ];
+    let corner = (
+        raster.west - raster.dx / 2.0,
+        raster.north + raster.dy / 2.0,
+    );
+    // Ascending tag order, as TIFF requires; StripOffsets is filled in below
+    let mut fields: Vec<(u16, Field)> = vec![
+        (256, Field::Long(raster.width as u32)),
+        (257, Field::Long(raster.height as u32)),
+        (258, short(32)), // bits per sample
+        (259, short(1)),  // no compression
+        (262, short(1)),  // black is zero
+        (273, Field::Long(0)),
+        (277, short(1)), // samples per pixel
+        (278, Field::Long(raster.height as u32)),
+        (279, Field::Long(image_len as u32)),
+        (284, short(1)), // planar configuration: chunky
+        (339, short(3)), // sample format: IEEE float
+        (33550, Field::Double(vec![raster.dx, raster.dy, 0.0])),
+        (
+            33922,
+            Field::Double(vec![0.0, 0.0, 0.0, corner.0, corner.1, 0.0]),
+        ),
+        (34735, Field::Short(geo_keys)),
+        (42113, Field::Ascii("nan")), // GDAL_NODATA
+    ];
+
+    // Header, then the directory, then values too long for their entry, then the image
+    let ifd_len = 2 + 12 * fields.len() + 4;
+    let extra_len: usize = fields
+        .iter()
+        .map(|(_, field)| field.bytes().len())
+        .filter(|&len| len > 4)
+        .map(|len| len + len % 2)
+        .sum();
+    let image_offset = 8 + ifd_len + extra_len;
+    if let Some((_, offset)) = fields.iter_mut().find(|(tag, _)| *tag == 273) {
+        *offset = Field::Long(image_offset as u32);
+    }
+
+    let mut head = Vec::with_capacity(image_offset);
+    head.extend(b"II");
+    head.extend(42u16.to_le_bytes());
+    head.extend(8u32.to_le_bytes());
+    head.extend((fields.len() as u16).to_le_bytes());
+    let mut extra = Vec::with_capacity(extra_len);
+    for (tag, field) in &fields {
+        let (kind, count) = field.kind();
+        let mut bytes = field.bytes();
+        head.extend(tag.to_le_bytes());
+        head.extend(kind.to_le_bytes());
+        head.extend(count.to_le_bytes());
+        if bytes.len() <= 4 {
+            bytes.resize(4, 0);
+            head.extend(bytes);
+        } else {
+            head.extend(((8 + ifd_len + extra.len()) as u32).to_le_bytes());
+            if bytes.len() % 2 == 1 {
+                bytes.push(0);
+            }
+            extra.extend(bytes);
+        }
+    }
+    head.extend(0u32.to_le_bytes()); // no further directories
+    head.extend(extra);
+    out.write_all(&head)?;
+
+    let mut row = Vec::with_capacity(raster.width * 4);
+    for values in raster.values.chunks(raster.width.max(1)) {
+        row.clear();
+        row.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+        out.write_all(&row)?;
+    }
+    out.flush()
+}
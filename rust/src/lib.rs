@@ -2,12 +2,54 @@
 //!
 //! Provides C-compatible functions for streaming GRIB2 files.
 //! Supports both file paths and in-memory byte arrays.
+//!
+//! Rust hosts can skip the FFI: `Grib2Reader::open` and `Grib2Reader::from_bytes`
+//! return a reader whose `messages` lists the indexed fields and whose `points`
+//! iterates the decoded values; the `grib2_*` functions wrap the same reader.
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
 
-use grib::Grib2SubmessageDecoder;
-use std::ffi::{c_char, c_double, c_uint, CStr, CString};
+use grib::codetables::grib2::Table4_4;
+use grib::{
+    FixedSurface, ForecastTime, Grib2SubmessageDecoder, GridDefinition, Identification, Name, Num,
+    ProdDefinition, ReprDefinition, SectionBody, SectionInfo, SeekableGrib2Reader, SubMessage,
+};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ffi::{c_char, c_double, c_int, c_uint, c_void, CStr, CString};
 use std::fs::File;
-use std::io::{BufReader, Cursor, Read, Seek};
-use std::ptr;
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::iter::Peekable;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Report a diagnostic through log::emit; compiled out without the `tracing` feature
+macro_rules! log_event {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        log::emit(log::Grib2LogLevel::$level, format_args!($($arg)*));
+    };
+}
+
+#[cfg(feature = "arrow")]
+mod arrow;
+mod cache;
+mod complex;
+mod encode;
+#[cfg(feature = "geotiff")]
+mod geotiff;
+#[cfg(feature = "tracing")]
+mod log;
+mod parameters;
+mod regrid;
+
+#[cfg(feature = "tracing")]
+pub use log::{Grib2LogCallback, Grib2LogLevel};
+pub use regrid::Grib2RegridMethod;
 
 /// A single data point from a GRIB2 file
 #[repr(C)]
@@ -19,9 +61,183 @@ pub struct Grib2DataPoint {
     pub parameter_category: u8,
     pub parameter_number: u8,
     pub forecast_time: i64,
+    pub reference_time: i64,
     pub surface_type: u8,
+    /// Physical level in the unit of code table 4.5 (Pa for isobaric, m above ground, ...)
     pub surface_value: c_double,
+    pub surface_type_2: u8,        // 255 when the level is not a layer
+    pub surface_value_2: c_double, // same units as surface_value
+    pub message_index: c_uint,     // GRIB message number within the file
+    pub submessage_index: c_uint,  // field number within the message
+    pub is_missing: bool,          // masked by the section 6 bitmap, value is NaN
+    pub ensemble_member: i32,      // perturbation number, -1 outside ensemble templates
+    pub statistical_process: u8,   // code table 4.10 (1 = accumulation), 255 if instantaneous
+    pub time_range_seconds: i64,   // length of the processed interval, 0 if instantaneous
+    pub kind: u8,                  // Grib2MessageKind
+    pub probability_type: i32,     // code table 4.9, -1 outside probability templates
+    pub threshold_lower: c_double, // NaN when missing or not a probability
+    pub threshold_upper: c_double,
+    pub percentile: i32,      // 0-100 in templates 4.6/4.10, -1 otherwise
+    pub source_index: c_uint, // file within grib2_open_multi, 0 for single-file readers
+    pub valid_time: i64, // unix seconds, end of the interval for statistical fields; 0 if unknown
+}
+
+impl Grib2DataPoint {
+    fn new(msg: &ParsedMessage, (lat, lon, value): (f64, f64, f64)) -> Self {
+        Grib2DataPoint {
+            latitude: lat,
+            longitude: lon,
+            value,
+            discipline: msg.discipline,
+            parameter_category: msg.parameter_category,
+            parameter_number: msg.parameter_number,
+            forecast_time: msg.forecast_time,
+            reference_time: msg.reference_time,
+            surface_type: msg.surface_type,
+            surface_value: msg.surface_value,
+            surface_type_2: msg.surface_type_2,
+            surface_value_2: msg.surface_value_2,
+            message_index: msg.message_index,
+            submessage_index: msg.submessage_index,
+            is_missing: value.is_nan(),
+            ensemble_member: msg.ensemble_member,
+            statistical_process: msg.statistical_process,
+            time_range_seconds: msg.time_range_seconds,
+            kind: msg.kind as u8,
+            probability_type: msg.probability_type,
+            threshold_lower: msg.threshold_lower,
+            threshold_upper: msg.threshold_upper,
+            percentile: msg.percentile,
+            source_index: msg.source_index,
+            valid_time: msg.valid_time,
+        }
+    }
+}
+
+/// Single-precision variant of Grib2DataPoint, GRIB values are f32 at the source
+#[repr(C)]
+pub struct Grib2DataPointF32 {
+    pub latitude: f32,
+    pub longitude: f32,
+    pub value: f32,
+    pub discipline: u8,
+    pub parameter_category: u8,
+    pub parameter_number: u8,
+    pub forecast_time: i64,
+    pub reference_time: i64,
+    pub surface_type: u8,
+    pub surface_value: f32,
+    pub surface_type_2: u8,
+    pub surface_value_2: f32,
     pub message_index: c_uint,
+    pub submessage_index: c_uint,
+    pub is_missing: bool,
+    pub ensemble_member: i32,
+    pub statistical_process: u8,
+    pub time_range_seconds: i64,
+    pub kind: u8,
+    pub probability_type: i32,
+    pub threshold_lower: f32,
+    pub threshold_upper: f32,
+    pub percentile: i32,
+    pub source_index: u32,
+    pub valid_time: i64,
+}
+
+impl Grib2DataPointF32 {
+    fn new(msg: &ParsedMessage, (lat, lon, value): (f64, f64, f64)) -> Self {
+        Grib2DataPointF32 {
+            latitude: lat as f32,
+            longitude: lon as f32,
+            value: value as f32,
+            discipline: msg.discipline,
+            parameter_category: msg.parameter_category,
+            parameter_number: msg.parameter_number,
+            forecast_time: msg.forecast_time,
+            reference_time: msg.reference_time,
+            surface_type: msg.surface_type,
+            surface_value: msg.surface_value as f32,
+            surface_type_2: msg.surface_type_2,
+            surface_value_2: msg.surface_value_2 as f32,
+            message_index: msg.message_index,
+            submessage_index: msg.submessage_index,
+            is_missing: value.is_nan(),
+            ensemble_member: msg.ensemble_member,
+            statistical_process: msg.statistical_process,
+            time_range_seconds: msg.time_range_seconds,
+            kind: msg.kind as u8,
+            probability_type: msg.probability_type,
+            threshold_lower: msg.threshold_lower as f32,
+            threshold_upper: msg.threshold_upper as f32,
+            percentile: msg.percentile,
+            source_index: msg.source_index,
+            valid_time: msg.valid_time,
+        }
+    }
+}
+
+/// What a message holds, from its product template and reference time significance
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grib2MessageKind {
+    Analysis = 0,
+    Forecast = 1, // deterministic, also ensemble means and spreads (templates 4.2/4.12)
+    EnsembleControl = 2,
+    EnsemblePerturbed = 3,
+    Probability = 4, // templates 4.5/4.9
+    Climatology = 5,
+    Unknown = 255,
+}
+
+/// Hand an array allocation to C; freed again with Vec::from_raw_parts(data, count, count)
+fn leak_vec<T>(items: Vec<T>) -> (*mut T, usize) {
+    if items.is_empty() {
+        return (ptr::null_mut(), 0);
+    }
+    // Boxed slice so capacity == count, as the free functions assume
+    let count = items.len();
+    (Box::into_raw(items.into_boxed_slice()) as *mut T, count)
+}
+
+/// Point batches handed to C and not freed yet: data pointer -> id of the producing reader
+/// Lets grib2_close notice batches the caller forgot, which would otherwise leak silently
+static OUTSTANDING_BATCHES: Mutex<BTreeMap<usize, u64>> = Mutex::new(BTreeMap::new());
+
+static NEXT_READER_ID: AtomicU64 = AtomicU64::new(0);
+
+fn outstanding_batches() -> MutexGuard<'static, BTreeMap<usize, u64>> {
+    // The map is never left half-updated, so a panic elsewhere doesn't invalidate it
+    OUTSTANDING_BATCHES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// leak_vec for point batches, recorded against the reader until the batch is freed
+fn leak_batch<T>(reader_id: u64, items: Vec<T>) -> (*mut T, usize) {
+    let (data, count) = leak_vec(items);
+    if !data.is_null() {
+        outstanding_batches().insert(data as usize, reader_id);
+    }
+    (data, count)
+}
+
+/// Free the points of a batch made by leak_batch, also after its reader is closed
+fn free_batch_data<T>(data: *mut T, count: usize) {
+    if data.is_null() {
+        return;
+    }
+    outstanding_batches().remove(&(data as usize));
+    unsafe {
+        let _ = Vec::from_raw_parts(data, count, count);
+    }
+}
+
+/// Number of batches from a reader that have not been freed yet
+fn outstanding_count(reader_id: u64) -> usize {
+    outstanding_batches()
+        .values()
+        .filter(|&&id| id == reader_id)
+        .count()
 }
 
 /// Batch of data points for efficient transfer
@@ -33,287 +249,5777 @@ pub struct Grib2Batch {
     pub error: *mut c_char,
 }
 
+/// Batch of single-precision data points
+#[repr(C)]
+pub struct Grib2BatchF32 {
+    pub data: *mut Grib2DataPointF32,
+    pub count: usize,
+    pub has_more: bool,
+    pub error: *mut c_char,
+}
+
+impl Grib2Batch {
+    fn failed(message: impl Into<String>) -> Self {
+        Grib2Batch {
+            data: ptr::null_mut(),
+            count: 0,
+            has_more: false,
+            error: error_string(message),
+        }
+    }
+}
+
+impl Grib2BatchF32 {
+    fn failed(message: impl Into<String>) -> Self {
+        Grib2BatchF32 {
+            data: ptr::null_mut(),
+            count: 0,
+            has_more: false,
+            error: error_string(message),
+        }
+    }
+}
+
+/// Data point with the integer its value was packed as, for code-table fields such
+/// as precipitation type where the number names a category rather than a quantity
+#[repr(C)]
+pub struct Grib2DataPointRaw {
+    pub point: Grib2DataPoint,
+    pub raw: i64,          // X in value = (R + X * 2^E) / 10^D, 0 when missing
+    pub categorical: bool, // the parameter's units are a code table
+}
+
+/// Batch of data points with their packed integers
+#[repr(C)]
+pub struct Grib2BatchRaw {
+    pub data: *mut Grib2DataPointRaw,
+    pub count: usize,
+    pub has_more: bool,
+    pub error: *mut c_char,
+}
+
+impl Grib2BatchRaw {
+    fn failed(message: impl Into<String>) -> Self {
+        Grib2BatchRaw {
+            data: ptr::null_mut(),
+            count: 0,
+            has_more: false,
+            error: error_string(message),
+        }
+    }
+}
+
+/// Data point together with the H3 cell containing it
+#[cfg(feature = "h3")]
+#[repr(C)]
+pub struct Grib2DataPointH3 {
+    pub point: Grib2DataPoint,
+    pub h3_cell: u64, // 0 when the coordinates aren't finite
+}
+
+/// Batch of data points with H3 cells
+#[cfg(feature = "h3")]
+#[repr(C)]
+pub struct Grib2BatchH3 {
+    pub data: *mut Grib2DataPointH3,
+    pub count: usize,
+    pub has_more: bool,
+    pub error: *mut c_char,
+}
+
+#[cfg(feature = "h3")]
+impl Grib2BatchH3 {
+    fn failed(message: impl Into<String>) -> Self {
+        Grib2BatchH3 {
+            data: ptr::null_mut(),
+            count: 0,
+            has_more: false,
+            error: error_string(message),
+        }
+    }
+}
+
+/// Metadata of one message, available without decoding any values
+#[repr(C)]
+pub struct Grib2MessageInfo {
+    pub discipline: u8,
+    pub parameter_category: u8,
+    pub parameter_number: u8,
+    pub forecast_time: i64,
+    pub reference_time: i64,
+    pub surface_type: u8,
+    pub surface_value: c_double,
+    pub surface_type_2: u8,
+    pub surface_value_2: c_double,
+    pub message_index: c_uint,
+    pub submessage_index: c_uint,
+    pub point_count: usize, // from section 3, ignores bbox crop and skipped missing points
+    pub ensemble_member: i32, // perturbation number, -1 outside ensemble templates
+    pub ensemble_size: i32, // forecasts in the ensemble, -1 when not given
+    pub statistical_process: u8, // code table 4.10, 255 if instantaneous
+    pub time_range_seconds: i64, // length of the processed interval, 0 if instantaneous
+    pub kind: u8,           // Grib2MessageKind
+    pub probability_type: i32, // code table 4.9, -1 outside probability templates
+    pub threshold_lower: c_double, // NaN when missing or not a probability
+    pub threshold_upper: c_double,
+    pub percentile: i32,      // 0-100 in templates 4.6/4.10, -1 otherwise
+    pub source_index: c_uint, // file within grib2_open_multi, 0 for single-file readers
+    pub valid_time: i64, // unix seconds, end of the interval for statistical fields; 0 if unknown
+}
+
+impl Grib2MessageInfo {
+    fn new(msg: &ParsedMessage) -> Self {
+        Grib2MessageInfo {
+            discipline: msg.discipline,
+            parameter_category: msg.parameter_category,
+            parameter_number: msg.parameter_number,
+            forecast_time: msg.forecast_time,
+            reference_time: msg.reference_time,
+            surface_type: msg.surface_type,
+            surface_value: msg.surface_value,
+            surface_type_2: msg.surface_type_2,
+            surface_value_2: msg.surface_value_2,
+            message_index: msg.message_index,
+            submessage_index: msg.submessage_index,
+            point_count: msg.num_points,
+            ensemble_member: msg.ensemble_member,
+            ensemble_size: msg.ensemble_size,
+            statistical_process: msg.statistical_process,
+            time_range_seconds: msg.time_range_seconds,
+            kind: msg.kind as u8,
+            probability_type: msg.probability_type,
+            threshold_lower: msg.threshold_lower,
+            threshold_upper: msg.threshold_upper,
+            percentile: msg.percentile,
+            source_index: msg.source_index,
+            valid_time: msg.valid_time,
+        }
+    }
+}
+
+/// Identification section (section 1) of the GRIB message holding a field
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Grib2Identification {
+    pub centre: u16, // common code table C-11, 65535 when missing
+    pub subcentre: u16,
+    pub year: u16, // reference time components as coded, UTC
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub reference_time_significance: u8, // code table 1.2
+    pub master_table_version: u8,        // code table 1.0, 255 when missing
+    pub local_table_version: u8,         // code table 1.1, 0 without local tables
+    pub production_status: u8,           // code table 1.3
+    pub data_type: u8,                   // code table 1.4
+}
+
+impl Grib2Identification {
+    /// What messages without a readable section 1 report
+    const MISSING: Self = Grib2Identification {
+        centre: 65535,
+        subcentre: 65535,
+        year: 0,
+        month: 0,
+        day: 0,
+        hour: 0,
+        minute: 0,
+        second: 0,
+        reference_time_significance: 255,
+        master_table_version: 255,
+        local_table_version: 255,
+        production_status: 255,
+        data_type: 255,
+    };
+
+    fn new(id: &Identification) -> Self {
+        // The year in octets 13-14, then month, day, hour, minute and second
+        let payload = id.iter().as_slice();
+        let octet = |pos: usize| payload.get(pos).copied().unwrap_or(0);
+        Grib2Identification {
+            centre: id.centre_id(),
+            subcentre: id.subcentre_id(),
+            year: u16::from_be_bytes([octet(7), octet(8)]),
+            month: octet(9),
+            day: octet(10),
+            hour: octet(11),
+            minute: octet(12),
+            second: octet(13),
+            reference_time_significance: id.ref_time_significance(),
+            master_table_version: id.master_table_version(),
+            local_table_version: id.local_table_version(),
+            production_status: id.prod_status(),
+            data_type: id.data_type(),
+        }
+    }
+}
+
+/// Why a message produced no points
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grib2SkipReason {
+    UnsupportedGrid = 0, // dropped at open, the grid template can't be mapped
+    NoCoordinates = 1,   // grid coordinates could not be computed
+    UnsupportedPacking = 2,
+    DecodeFailed = 3, // malformed or truncated data sections
+    OpenFailed = 4,   // grib2_open_multi skipped the whole file
+}
+
+/// One entry of grib2_get_skipped
+#[repr(C)]
+pub struct Grib2SkippedMessage {
+    pub message_index: u32,
+    pub submessage_index: u32,
+    pub reason: Grib2SkipReason,
+    pub error: *mut c_char, // freed with the array by grib2_free_skipped
+    pub source_index: u32,  // file within grib2_open_multi, 0 for single-file readers
+}
+
+/// Reason and error text for a message that produced no points
+type SkipCause = (Grib2SkipReason, String);
+
+/// A failure to unpack values: unsupported packing templates vs everything else
+fn unpack_error(e: grib::GribError) -> SkipCause {
+    use grib::{DecodeError, GribError};
+    let reason = match e {
+        GribError::NotSupported(_)
+        | GribError::DecodeError(
+            DecodeError::TemplateNumberUnsupported | DecodeError::BitMapIndicatorUnsupported,
+        ) => Grib2SkipReason::UnsupportedPacking,
+        _ => Grib2SkipReason::DecodeFailed,
+    };
+    (reason, format!("Failed to unpack values: {e}"))
+}
+
+/// Why a packing template can't be unpacked in this build, if its codec feature is off
+fn disabled_codec(template: u16) -> Option<String> {
+    let (codec, feature) = match template {
+        40 if cfg!(not(feature = "jpeg2000")) => ("JPEG2000", "jpeg2000"),
+        41 if cfg!(not(feature = "png")) => ("PNG", "png"),
+        _ => return None,
+    };
+    Some(format!(
+        "{codec} packing (template 5.{template}) requires the `{feature}` feature"
+    ))
+}
+
+/// A message that was dropped at open or failed to decode while streaming
+#[derive(PartialEq)]
+struct SkippedMessage {
+    source_index: u32,
+    message_index: u32,
+    submessage_index: u32,
+    reason: Grib2SkipReason,
+    error: String,
+}
+
+/// Any seekable byte source the reader can keep open for lazy decoding
+trait GribSource: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> GribSource for T {}
+
+type GribFile = grib::Grib2<SeekableGrib2Reader<SharedSource>>;
+
+/// A source shared by the grib parser and read_payload, for the section payloads
+/// the parser doesn't expose (e.g. section 7 of complex packing it can't unpack)
+#[derive(Clone)]
+struct SharedSource(Arc<Mutex<Box<dyn GribSource>>>);
+
+impl SharedSource {
+    fn new(source: Box<dyn GribSource>) -> Self {
+        SharedSource(Arc::new(Mutex::new(source)))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Box<dyn GribSource>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Bytes of a section after its 5-octet header, so section octet n (numbered from
+    /// 1, as in the WMO tables) is at index n - 6. The payloads the grib crate exposes
+    /// through `iter().as_slice()` start at the same octet and are indexed the same way
+    fn read_payload(&self, section: &SectionInfo) -> std::io::Result<Vec<u8>> {
+        let mut source = self.lock();
+        source.seek(SeekFrom::Start(section.offset as u64 + 5))?;
+        let mut payload = vec![0; section.size.saturating_sub(5)];
+        source.read_exact(&mut payload)?;
+        Ok(payload)
+    }
+}
+
+impl Read for SharedSource {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.lock().read(buf)
+    }
+}
+
+impl Seek for SharedSource {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.lock().seek(pos)
+    }
+}
+
+/// Reads up to `len` bytes into `buf` and returns how many it read, 0 at the end
+/// of the data and negative on failure
+pub type Grib2ReadCallback = extern "C" fn(user_data: *mut c_void, buf: *mut u8, len: usize) -> i64;
+
+/// Moves to `offset` from the start (whence 0), the current position (1) or the
+/// end (2), as fseek does; returns the new position from the start, negative on failure
+pub type Grib2SeekCallback =
+    extern "C" fn(user_data: *mut c_void, offset: i64, whence: c_int) -> i64;
+
+/// Host I/O callbacks, e.g. over DuckDB's FileSystem, as a Read + Seek source
+struct CallbackSource {
+    read: Grib2ReadCallback,
+    seek: Grib2SeekCallback,
+    user_data: *mut c_void,
+}
+
+// user_data belongs to the host, which lets any thread using the reader call into it
+unsafe impl Send for CallbackSource {}
+
+impl Read for CallbackSource {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let count = (self.read)(self.user_data, buf.as_mut_ptr(), buf.len());
+        match usize::try_from(count) {
+            Ok(count) if count <= buf.len() => Ok(count),
+            Ok(count) => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "read callback returned {} bytes for {} requested",
+                    count,
+                    buf.len()
+                ),
+            )),
+            Err(_) => Err(std::io::Error::other(format!(
+                "read callback failed with {}",
+                count
+            ))),
+        }
+    }
+}
+
+impl Seek for CallbackSource {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => {
+                let offset = i64::try_from(offset).map_err(|_| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek offset too large")
+                })?;
+                (offset, 0)
+            }
+            SeekFrom::Current(offset) => (offset, 1),
+            SeekFrom::End(offset) => (offset, 2),
+        };
+        let position = (self.seek)(self.user_data, offset, whence);
+        u64::try_from(position)
+            .map_err(|_| std::io::Error::other(format!("seek callback failed with {}", position)))
+    }
+}
+
 /// Opaque reader handle for streaming
+///
+/// A reader is `Send` but not `Sync`: it may be created on one thread and used on
+/// another, but calls on the same handle must not overlap. Every read advances the
+/// cursor (`current_message`, `current_point`, `stream`) through `&mut self`, so e.g.
+/// two concurrent `grib2_read_batch` calls on one handle are a data race; the `Mutex`
+/// around the shared source doesn't cover that state. Parallel scans should open one
+/// reader per thread.
 pub struct Grib2Reader {
+    id: u64,                                 // tags the batches this reader hands out
+    files: Vec<GribFile>,                    // one per source file, see ParsedMessage::file
+    sources: Vec<SharedSource>,              // what files[i] parses, for raw section reads
+    content: Vec<Option<cache::ContentKey>>, // identity of files[i] for the decode cache
     messages: Vec<ParsedMessage>,
+    filter: MessageFilter,
     current_message: usize,
     current_point: usize,
+    stream: Option<PointStream>, // the message under the cursor, unless it is already decoded
+    skipped: Vec<SkippedMessage>, // diagnostics for grib2_get_skipped
+    wind: Option<WindMessages>,  // set by derive_wind
+    point_budget: usize,         // most points handed out between resets, usize::MAX for no cap
+    emitted: usize,              // points handed out since open or the last reset
+}
+
+/// Points under a reader's cursor, from `Grib2Reader::points`
+/// Dropping it part way leaves the cursor there, so a later call resumes
+pub struct Points<'a> {
+    reader: &'a mut Grib2Reader,
+}
+
+impl Iterator for Points<'_> {
+    type Item = Grib2DataPoint;
+
+    fn next(&mut self) -> Option<Grib2DataPoint> {
+        let mut point = None;
+        self.reader.visit_points(1, |msg, p| {
+            point = Some(Grib2DataPoint::new(msg, p));
+            true
+        });
+        point
+    }
+}
+
+/// Points with the grid row each lies on, 0 for all unless rows are tracked
+type PointIter = Box<dyn Iterator<Item = (usize, (f64, f64, f64))> + Send>;
+
+/// Points of one message, unpacked as read_batch pulls them
+/// Holds the packed section 7 payload but never the whole unpacked message
+struct PointStream {
+    points: Peekable<PointIter>,
+    rows: bool,                         // points carry their grid row, see next_row
+    row: VecDeque<(f64, f64, f64)>,     // pulled ahead of the cursor by next_row
+    decoder: Option<NonNull<Unpacker>>, // owned, borrowed by `points`
+}
+
+// The decoder pointer is an owned Box, so the stream moves between threads with the reader
+unsafe impl Send for PointStream {}
+
+/// A finished stream, also what a message that fails to decode yields
+fn no_points() -> Peekable<PointIter> {
+    let points: PointIter = Box::new(std::iter::empty());
+    points.peekable()
+}
+
+impl PointStream {
+    fn empty() -> Self {
+        PointStream {
+            points: no_points(),
+            rows: false,
+            row: VecDeque::new(),
+            decoder: None,
+        }
+    }
+
+    /// Stream the values of a packed message; fails if its packing can't be decoded
+    fn decode(
+        latlons: GridPoints,
+        decoder: Unpacker,
+        filter: MessageFilter,
+    ) -> Result<Self, SkipCause> {
+        let decoder = NonNull::from(Box::leak(Box::new(decoder)));
+        // Owned by the stream from here on, so an early return still frees the decoder
+        let mut stream = PointStream {
+            points: no_points(),
+            rows: filter.row_len.is_some(),
+            row: VecDeque::new(),
+            decoder: Some(decoder),
+        };
+        // SAFETY: the decoder lives on the heap until Drop, which drops `points` first
+        let values = unsafe { decoder.as_ref() }.values()?;
+        stream.points = filter.stream(latlons, values).peekable();
+        Ok(stream)
+    }
+
+    /// Stream values that are already unpacked, e.g. derived wind fields or cached ones
+    fn from_values(latlons: GridPoints, values: Arc<[f32]>, filter: MessageFilter) -> Self {
+        let values = (0..values.len()).map(move |k| values[k]);
+        PointStream {
+            points: filter.stream(latlons, values).peekable(),
+            rows: filter.row_len.is_some(),
+            row: VecDeque::new(),
+            decoder: None,
+        }
+    }
+
+    fn next(&mut self) -> Option<(f64, f64, f64)> {
+        self.row
+            .pop_front()
+            .or_else(|| self.points.next().map(|(_, point)| point))
+    }
+
+    /// Points left in the row under the cursor, pulling the whole next row ahead once
+    /// the last is out; 0 at the end
+    fn next_row(&mut self) -> usize {
+        if self.row.is_empty() {
+            if let Some((row, point)) = self.points.next() {
+                self.row.push_back(point);
+                while let Some((_, point)) = self.points.next_if(|&(next, _)| next == row) {
+                    self.row.push_back(point);
+                }
+            }
+        }
+        self.row.len()
+    }
+
+    fn is_exhausted(&mut self) -> bool {
+        self.row.is_empty() && self.points.peek().is_none()
+    }
+}
+
+impl Drop for PointStream {
+    fn drop(&mut self) {
+        // The values iterator borrows the decoder, so it has to go first
+        self.points = no_points();
+        if let Some(decoder) = self.decoder.take() {
+            // SAFETY: leaked in `decode` and no longer borrowed
+            drop(unsafe { Box::from_raw(decoder.as_ptr()) });
+        }
+    }
+}
+
+/// Where derive_wind appended its synthetic messages
+#[derive(Clone, Copy)]
+struct WindMessages {
+    speed: usize, // index of the first speed message
+    direction: usize,
+    count: usize,
 }
 
+// Fails to compile if a field ever stops being Send
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    assert_send::<Grib2Reader>();
+};
+
+/// Message metadata read at open time; values are decoded on demand
+#[derive(Clone)]
 struct ParsedMessage {
+    file: usize,       // position in Grib2Reader::files
+    submessage: usize, // position in files[file].iter()
     discipline: u8,
     parameter_category: u8,
     parameter_number: u8,
     forecast_time: i64,
+    reference_time: i64, // unix seconds from section 1
+    valid_time: i64,     // unix seconds, 0 when unknown
+    identification: Grib2Identification,
+    generating_process: u8, // centre-defined model identifier, 255 when missing
+    packing_template: u16,  // data representation template from section 5
+    bits_per_value: u8,     // 255 when the template is not one we know
+    precision_bits: u8,     // see precision_bits()
+    packing_scale: Option<(f32, i16, i16)>, // reference R, binary scale E, decimal scale D
     surface_type: u8,
     surface_value: f64,
+    surface_type_2: u8,
+    surface_value_2: f64,
     message_index: u32,
-    points: Vec<(f64, f64, f64)>, // (lat, lon, value)
+    submessage_index: u32,
+    submessage_count: u32, // fields in the GRIB message, also ones filtered out or skipped
+    extent: (u64, u64),    // byte offset and length of the whole GRIB message in the source
+    source_index: u32,     // file within grib2_open_multi
+    ensemble_member: i32,  // -1 outside ensemble templates
+    ensemble_size: i32,
+    statistical_process: u8, // 255 for instantaneous fields
+    time_range_seconds: i64,
+    kind: Grib2MessageKind,
+    probability_type: i32, // -1 outside templates 4.5/4.9
+    threshold_lower: f64,
+    threshold_upper: f64,
+    percentile: i32, // -1 outside templates 4.6/4.10
+    grid: GridInfo,
+    grid_hash: u64, // GridInfo::definition_hash of section 3
+    latlon_grid: Option<LatLonGrid>,
+    bounds: Option<(f64, f64, f64, f64)>, // min lat, west, max lat, east; see grid_bounds
+    num_points: usize,                    // from section 3, before any bbox crop
+    decoded: bool,
+    duplicate: bool, // another message holds the same field and the dup policy keeps that one
+    points: Vec<(f64, f64, f64)>, // (lat, lon, value), empty until decoded
+    stats: Option<ValueStats>, // cached the first time the message is decoded
+    derived: Option<Derived>, // synthetic message computed from other messages
 }
 
-impl Grib2Reader {
-    fn from_reader<R: Read + Seek>(reader: R) -> Result<Self, String> {
-        let grib2 = grib::from_reader(reader).map_err(|e| format!("Failed to parse GRIB: {}", e))?;
+impl ParsedMessage {
+    /// Table entry of the parameter, read against the message's own section 1
+    fn parameter(&self) -> Option<&'static parameters::Parameter> {
+        parameters::lookup_in(
+            self.discipline,
+            self.parameter_category,
+            self.parameter_number,
+            &self.identification,
+        )
+    }
+}
 
-        let mut messages = Vec::new();
+/// A field computed from the U and V wind messages at indices `u` and `v`
+#[derive(Clone, Copy)]
+enum Derived {
+    WindSpeed { u: usize, v: usize },
+    WindDirection { u: usize, v: usize },
+}
 
-        for (msg_idx, submessage) in grib2.iter() {
-            let discipline = submessage.indicator().discipline;
-            let prod_def = submessage.prod_def();
+impl Derived {
+    fn sources(self) -> (usize, usize) {
+        match self {
+            Derived::WindSpeed { u, v } | Derived::WindDirection { u, v } => (u, v),
+        }
+    }
 
-            let param_cat = prod_def.parameter_category().unwrap_or(0);
-            let param_num = prod_def.parameter_number().unwrap_or(0);
+    fn combine(self, u: f32, v: f32) -> f32 {
+        match self {
+            Derived::WindSpeed { .. } => u.hypot(v),
+            // Meteorological convention: the direction the wind blows from,
+            // clockwise from north in (0, 360], with 0 reserved for calm
+            Derived::WindDirection { .. } if u == 0.0 && v == 0.0 => 0.0,
+            Derived::WindDirection { .. } => {
+                let direction = (270.0 - v.atan2(u).to_degrees()).rem_euclid(360.0);
+                if direction == 0.0 {
+                    360.0
+                } else {
+                    direction
+                }
+            }
+        }
+    }
+}
 
-            let forecast_time = prod_def
-                .forecast_time()
-                .map(|ft| ft.value as i64)
-                .unwrap_or(0);
+/// Summary of the non-missing values of one message
+#[derive(Clone, Copy)]
+struct ValueStats {
+    min: f64,
+    max: f64,
+    count: usize,
+    mean: f64,
+}
 
-            let (surface_type, surface_value) = prod_def
-                .fixed_surfaces()
-                .map(|(first, _)| (first.surface_type, first.value() as f64))
-                .unwrap_or((0, 0.0));
+impl ValueStats {
+    fn from_points(points: &[(f64, f64, f64)]) -> Self {
+        let mut stats = ValueStats {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            count: 0,
+            mean: f64::NAN,
+        };
+        let mut sum = 0.0;
+        for &(_, _, value) in points.iter().filter(|p| !p.2.is_nan()) {
+            stats.min = stats.min.min(value);
+            stats.max = stats.max.max(value);
+            stats.count += 1;
+            sum += value;
+        }
+        if stats.count == 0 {
+            stats.min = f64::NAN;
+            stats.max = f64::NAN;
+        } else {
+            stats.mean = sum / stats.count as f64;
+        }
+        stats
+    }
+}
 
-            let latlons = match submessage.latlons() {
-                Ok(ll) => ll,
-                Err(_) => continue,
-            };
+/// Geographic crop applied while collecting points
+#[derive(Clone, Copy)]
+struct BoundingBox {
+    min_lat: f64,
+    min_lon: f64,
+    max_lat: f64,
+    max_lon: f64,
+}
 
-            let decoder = match Grib2SubmessageDecoder::from(submessage) {
-                Ok(d) => d,
-                Err(_) => continue,
-            };
+impl BoundingBox {
+    /// Longitudes are normalized to -180..180; min_lon > max_lon wraps across the antimeridian
+    /// A max_lon of 180 stays 180 so the box still reaches the antimeridian
+    fn new(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Self {
+        BoundingBox {
+            min_lat,
+            min_lon: normalize_longitude(min_lon, Grib2LonConvention::Signed),
+            max_lat,
+            max_lon: match normalize_longitude(max_lon, Grib2LonConvention::Signed) {
+                -180.0 if max_lon > 0.0 => 180.0,
+                max_lon => max_lon,
+            },
+        }
+    }
 
-            let values = match decoder.dispatch() {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
+    fn contains(&self, lat: f64, lon: f64) -> bool {
+        if lat < self.min_lat || lat > self.max_lat {
+            return false;
+        }
+        let within = |lon: f64| {
+            if self.min_lon <= self.max_lon {
+                lon >= self.min_lon && lon <= self.max_lon
+            } else {
+                lon >= self.min_lon || lon <= self.max_lon
+            }
+        };
+        // Points on the antimeridian are -180 but also close boxes ending at 180
+        within(lon) || (lon == -180.0 && within(180.0))
+    }
+}
+
+/// Range that emitted longitudes are reported in
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Grib2LonConvention {
+    #[default]
+    Signed = 0, // [-180, 180)
+    Unsigned = 1, // [0, 360), as stored in GRIB2
+}
+
+/// Which of several messages holding the same field stream, see grib2_set_dup_policy
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Grib2DupPolicy {
+    #[default]
+    KeepAll = 0,
+    KeepFirst = 1, // the first in file order, e.g. the original of a revised field
+    KeepLast = 2,  // the last in file order, e.g. the correction appended after it
+}
+
+/// Selects which messages get decoded (None matches anything)
+#[derive(Default, Clone, Copy)]
+struct MessageFilter {
+    discipline: Option<u8>,
+    parameter_category: Option<u8>,
+    parameter_number: Option<u8>,
+    bbox: Option<BoundingBox>,
+    skip_missing: bool, // drop bitmap-masked points instead of emitting NaN
+    lead_time: Option<(i64, i64)>, // inclusive forecast time range in seconds
+    valid_time: Option<(i64, i64)>, // inclusive reference + forecast time range, unix seconds
+    lon_convention: Grib2LonConvention,
+    sort_by_coordinate: bool, // emit each message north to south, then west to east
+    dedup_wraparound: bool,   // drop the last column of grids that repeat the first
+    seam: Option<LatLonGrid>, // grid of the message being filtered, when it has that column
+    row_aligned: bool,        // keep batches from splitting grid rows, see grib2_set_row_aligned
+    row_len: Option<usize>,   // row length of the message being filtered, when row_aligned
+}
+
+impl MessageFilter {
+    /// Build from C arguments where values outside 0..=255 (e.g. -1) mean "any"
+    fn from_c(discipline: c_int, parameter_category: c_int, parameter_number: c_int) -> Self {
+        MessageFilter {
+            discipline: u8::try_from(discipline).ok(),
+            parameter_category: u8::try_from(parameter_category).ok(),
+            parameter_number: u8::try_from(parameter_number).ok(),
+            ..MessageFilter::default()
+        }
+    }
+
+    /// The filter for one message: with dedup_wraparound, remembers the grid whose
+    /// repeated seam column filter_points drops, and with row_aligned its row length
+    fn for_message(self, msg: &ParsedMessage) -> Self {
+        let seam = msg
+            .latlon_grid
+            .filter(|grid| self.dedup_wraparound && grid.repeats_first_column());
+        let row_len = msg
+            .grid
+            .row_len(msg.num_points)
+            .filter(|_| self.row_aligned);
+        MessageFilter {
+            seam,
+            row_len,
+            ..self
+        }
+    }
+
+    /// Row alignment keeps scanning order, so it wins over sort_by_coordinate
+    fn sorts(&self) -> bool {
+        self.sort_by_coordinate && !self.row_aligned
+    }
+
+    /// Pair unpacked values with coordinates, applying the missing-value policy and bbox
+    /// The bbox is checked on [-180, 180) longitudes before the convention is applied
+    /// Pure CPU work, safe to run off-thread
+    fn collect_points(
+        &self,
+        latlons: GridPoints,
+        values: impl Iterator<Item = f32>,
+    ) -> Vec<(f64, f64, f64)> {
+        let mut points: Vec<_> = self
+            .filter_points(latlons, values)
+            .map(|(_, point)| point)
+            .collect();
+        if self.sorts() {
+            // Descending latitude, then ascending longitude in the output convention
+            points.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.total_cmp(&b.1)));
+        }
+        points
+    }
+
+    /// filter_points as a stream; sorting needs the whole message, so it is collected
+    fn stream(
+        self,
+        latlons: GridPoints,
+        values: impl Iterator<Item = f32> + Send + 'static,
+    ) -> PointIter {
+        if self.sorts() {
+            let points = self.collect_points(latlons, values);
+            Box::new(points.into_iter().map(|point| (0, point)))
+        } else {
+            Box::new(self.filter_points(latlons, values))
+        }
+    }
 
-            let flat_index = (msg_idx.0 * 1000 + msg_idx.1) as u32;
+    /// Lazy form of collect_points; owns a copy of the filter so it can outlive the reader borrow
+    /// Each point comes with its grid row when rows are tracked, 0 otherwise
+    fn filter_points(
+        self,
+        latlons: GridPoints,
+        values: impl Iterator<Item = f32>,
+    ) -> impl Iterator<Item = (usize, (f64, f64, f64))> {
+        latlons
+            .zip(values)
+            .enumerate()
+            .filter_map(move |(k, ((lat, lon), value))| {
+                let (lat, lon) = (lat as f64, lon as f64);
+                let signed_lon = normalize_longitude(lon, Grib2LonConvention::Signed);
+                if self.skip_missing && value.is_nan() {
+                    return None;
+                }
+                if let Some(grid) = &self.seam {
+                    if grid.cell(k).0 == grid.ni - 1 {
+                        return None;
+                    }
+                }
+                let row = self.row_len.map_or(0, |len| k / len);
+                match &self.bbox {
+                    Some(bbox) if !bbox.contains(lat, signed_lon) => None,
+                    _ => Some((
+                        row,
+                        (
+                            lat,
+                            normalize_longitude(lon, self.lon_convention),
+                            value as f64,
+                        ),
+                    )),
+                }
+            })
+    }
+
+    /// Messages whose lead time is unknown (e.g. given in months) fail any time range
+    fn matches_time(&self, reference_time: i64, lead_seconds: Option<i64>) -> bool {
+        let in_range = |range: Option<(i64, i64)>, offset: i64| match (range, lead_seconds) {
+            (None, _) => true,
+            (Some((min, max)), Some(lead)) => (min..=max).contains(&(offset + lead)),
+            (Some(_), None) => false,
+        };
+        in_range(self.lead_time, 0) && in_range(self.valid_time, reference_time)
+    }
+
+    /// A message without a category or number (see `field_parameter`) only passes a
+    /// filter that leaves that part unset
+    fn matches(
+        &self,
+        discipline: u8,
+        parameter_category: Option<u8>,
+        parameter_number: Option<u8>,
+    ) -> bool {
+        self.discipline.is_none_or(|d| d == discipline)
+            && self
+                .parameter_category
+                .is_none_or(|c| Some(c) == parameter_category)
+            && self
+                .parameter_number
+                .is_none_or(|n| Some(n) == parameter_number)
+    }
+}
+
+/// Category and number of the parameter a product template describes. Template 4.254
+/// carries CCITT IA5 text rather than a field, and templates the grib crate doesn't
+/// know have no category or number it can locate
+fn field_parameter(
+    template: u16,
+    category: Option<u8>,
+    number: Option<u8>,
+) -> (Option<u8>, Option<u8>) {
+    match template {
+        254 => (None, None),
+        _ => (category, number),
+    }
+}
+
+/// Native grid shape read from the grid definition template (section 3)
+#[derive(Default, Clone, Copy)]
+struct GridInfo {
+    template: u16, // grid definition template number (code table 3.1)
+    ni: u32,       // points along a parallel
+    nj: u32,       // points along a meridian
+    scan_mode: u8,
+}
+
+impl GridInfo {
+    fn from_grid_def(grid_def: &GridDefinition) -> Self {
+        let template = grid_def.grid_tmpl_num();
+        let payload = grid_def.iter().as_slice();
+        // Ni/Nj sit at octets 31-38 in all these templates
+        let scan_mode_pos = match grid_def.grid_tmpl_num() {
+            0..=3 | 40..=43 => Some(66),
+            10 => Some(54),
+            20 | 30 | 31 => Some(59),
+            _ => None,
+        };
+        scan_mode_pos
+            .and_then(|pos| {
+                Some(GridInfo {
+                    template,
+                    ni: be_u32(payload, 25)?,
+                    nj: be_u32(payload, 29)?,
+                    scan_mode: *payload.get(pos)?,
+                })
+            })
+            .unwrap_or(GridInfo {
+                template,
+                ..GridInfo::default()
+            })
+    }
+
+    /// Fingerprint of the whole grid definition, equal for messages on the same grid
+    fn definition_hash(grid_def: &GridDefinition) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        grid_def.iter().as_slice().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn is_known(&self) -> bool {
+        self.ni > 0 && self.nj > 0
+    }
+
+    /// Points per row along the consecutive scanning direction: Ni, or Nj when
+    /// points run along meridians. None without fixed rows, e.g. reduced grids
+    fn row_len(&self, num_points: usize) -> Option<usize> {
+        let (ni, nj) = (self.ni as usize, self.nj as usize);
+        if !self.is_known() || ni.checked_mul(nj) != Some(num_points) {
+            return None;
+        }
+        Some(if self.scan_mode & 0x20 == 0 { ni } else { nj })
+    }
+
+    /// Position of cell (i, j) in the decoded value array, None outside the grid
+    fn flat_index(&self, i: usize, j: usize) -> Option<usize> {
+        let (ni, nj) = (self.ni as usize, self.nj as usize);
+        (i < ni && j < nj).then(|| scan_position(ni, nj, self.scan_mode, i, j))
+    }
+}
+
+/// Offset of cell (i, j) in scan order, with i and j counted from the first grid
+/// point along the scanning directions of flag table 3.4
+fn scan_position(ni: usize, nj: usize, scan_mode: u8, i: usize, j: usize) -> usize {
+    let alternating = scan_mode & 0x10 != 0;
+    if scan_mode & 0x20 == 0 {
+        let i = if alternating && j % 2 == 1 { ni - 1 - i } else { i };
+        j * ni + i
+    } else {
+        let j = if alternating && i % 2 == 1 { nj - 1 - j } else { j };
+        i * nj + j
+    }
+}
+
+/// Regular lat/lon grid geometry (template 3.0) in degrees
+#[derive(Clone, Copy)]
+struct LatLonGrid {
+    ni: usize,
+    nj: usize,
+    first_lat: f64,
+    first_lon: f64,
+    lat_step: f64, // signed, in scanning direction
+    lon_step: f64, // signed, in scanning direction
+    scan_mode: u8,
+}
+
+impl LatLonGrid {
+    fn from_grid_def(grid_def: &GridDefinition) -> Option<Self> {
+        if grid_def.grid_tmpl_num() != 0 {
+            return None;
+        }
+        Self::from_payload(grid_def.iter().as_slice())
+    }
+
+    /// Read the geometry of template 3.0, or the identical leading part of template 3.1
+    fn from_payload(payload: &[u8]) -> Option<Self> {
+        let ni = be_u32(payload, 25)? as usize;
+        let nj = be_u32(payload, 29)? as usize;
+        if ni == 0 || nj == 0 || ni == u32::MAX as usize {
+            return None;
+        }
+        let first_lat = grib_i32(payload, 41)? as f64 / 1e6;
+        let first_lon = grib_i32(payload, 45)? as f64 / 1e6;
+        let last_lat = grib_i32(payload, 50)? as f64 / 1e6;
+        let last_lon = grib_i32(payload, 54)? as f64 / 1e6;
+        let scan_mode = *payload.get(66)?;
+
+        // Grids crossing 0° can encode Lo2 < Lo1 while still scanning eastwards
+        let mut lon_span = last_lon - first_lon;
+        if scan_mode & 0x80 == 0 && lon_span < 0.0 {
+            lon_span += 360.0;
+        } else if scan_mode & 0x80 != 0 && lon_span > 0.0 {
+            lon_span -= 360.0;
+        }
+        let step = |span: f64, n: usize| if n > 1 { span / (n - 1) as f64 } else { 0.0 };
+
+        Some(LatLonGrid {
+            ni,
+            nj,
+            first_lat,
+            first_lon,
+            lat_step: step(last_lat - first_lat, nj),
+            lon_step: step(lon_span, ni),
+            scan_mode,
+        })
+    }
+
+    /// True when the columns cover the full circle so column ni-1 neighbours column 0
+    fn is_global(&self) -> bool {
+        let step = self.lon_step.abs();
+        step > 0.0 && (step * self.ni as f64 - 360.0).abs() < step / 2.0
+    }
+
+    /// Fractional (i, j) grid coordinates of a location, None when outside the grid
+    fn fractional_index(&self, lat: f64, lon: f64) -> Option<(f64, f64)> {
+        const EPS: f64 = 1e-6;
+        let fj = if self.lat_step != 0.0 {
+            (lat - self.first_lat) / self.lat_step
+        } else if (lat - self.first_lat).abs() < EPS {
+            0.0
+        } else {
+            return None;
+        };
+        let fi = if self.lon_step > 0.0 {
+            (lon - self.first_lon).rem_euclid(360.0) / self.lon_step
+        } else if self.lon_step < 0.0 {
+            (self.first_lon - lon).rem_euclid(360.0) / -self.lon_step
+        } else if (lon - self.first_lon).rem_euclid(360.0) < EPS {
+            0.0
+        } else {
+            return None;
+        };
+
+        let max_i = if self.is_global() { self.ni as f64 } else { (self.ni - 1) as f64 };
+        if fj < -EPS || fj > (self.nj - 1) as f64 + EPS || fi > max_i + EPS {
+            return None;
+        }
+        Some((fi.max(0.0), fj.clamp(0.0, (self.nj - 1) as f64)))
+    }
+
+    /// True when the last column repeats the first one 360° further on, as in
+    /// global grids listing both 0° and 360°
+    fn repeats_first_column(&self) -> bool {
+        let step = self.lon_step.abs();
+        self.ni > 1 && step > 0.0 && (step * (self.ni - 1) as f64 - 360.0).abs() < step / 2.0
+    }
+
+    /// Grid cell (i, j) of the k-th point in scan order
+    fn cell(&self, k: usize) -> (usize, usize) {
+        let alternating = self.scan_mode & 0x10 != 0;
+        if self.scan_mode & 0x20 == 0 {
+            let (i, j) = (k % self.ni, k / self.ni);
+            (
+                if alternating && j % 2 == 1 {
+                    self.ni - 1 - i
+                } else {
+                    i
+                },
+                j,
+            )
+        } else {
+            let (i, j) = (k / self.nj, k % self.nj);
+            (
+                i,
+                if alternating && i % 2 == 1 {
+                    self.nj - 1 - j
+                } else {
+                    j
+                },
+            )
+        }
+    }
+
+    /// Coordinates of all points in scan order, longitudes not normalized
+    fn points(self) -> impl Iterator<Item = (f64, f64)> + Send {
+        (0..self.ni * self.nj).map(move |k| {
+            let (i, j) = self.cell(k);
+            (
+                self.first_lat + j as f64 * self.lat_step,
+                self.first_lon + i as f64 * self.lon_step,
+            )
+        })
+    }
+
+    /// Position of grid cell (i, j) in the decoded value array, honoring the scanning mode
+    fn flat_index(&self, i: usize, j: usize) -> usize {
+        let i = if self.is_global() { i % self.ni } else { i.min(self.ni - 1) };
+        scan_position(self.ni, self.nj, self.scan_mode, i, j)
+    }
+}
+
+/// Coordinates of every grid point in scan order
+type GridPoints = Box<dyn Iterator<Item = (f32, f32)> + Send>;
+
+/// Regular or reduced Gaussian grid (template 3.40)
+/// Reduced grids leave Ni missing and list the points of each parallel after the template
+struct GaussianGrid {
+    n: usize, // parallels between a pole and the equator
+    first_lat: f64,
+    last_lat: f64,
+    first_lon: f64,
+    lon_span: f64, // positive, in scanning direction
+    scan_mode: u8,
+    row_points: Vec<usize>,
+}
+
+impl GaussianGrid {
+    fn from_grid_def(grid_def: &GridDefinition) -> Option<Self> {
+        if grid_def.grid_tmpl_num() != 40 {
+            return None;
+        }
+        let payload = grid_def.iter().as_slice();
+        let ni = be_u32(payload, 25)?;
+        let nj = be_u32(payload, 29)? as usize;
+        let n = be_u32(payload, 62)? as usize;
+        let scan_mode = *payload.get(66)?;
+        // Rows of different lengths only make sense when consecutive points run along a parallel
+        // Operational grids stay well below N8000; larger values are corrupt headers
+        if n == 0 || n > 10_000 || nj == 0 || nj > 2 * n || scan_mode & 0x20 != 0 {
+            return None;
+        }
+
+        let row_points = if ni == u32::MAX {
+            // Octet 11 is the width of each list entry, octet 12 its meaning (1 = points per parallel)
+            let width = *payload.get(5)? as usize;
+            if width == 0 || *payload.get(6)? != 1 {
+                return None;
+            }
+            payload
+                .get(67..67 + width * nj)?
+                .chunks(width)
+                .map(|entry| entry.iter().fold(0, |acc, b| acc << 8 | *b as usize))
+                .collect()
+        } else {
+            vec![ni as usize; nj]
+        };
+
+        let first_lon = grib_i32(payload, 45)? as f64 / 1e6;
+        let last_lon = grib_i32(payload, 54)? as f64 / 1e6;
+        let mut lon_span = if scan_mode & 0x80 == 0 {
+            last_lon - first_lon
+        } else {
+            first_lon - last_lon
+        };
+        if lon_span < 0.0 {
+            lon_span += 360.0;
+        }
+
+        Some(GaussianGrid {
+            n,
+            first_lat: grib_i32(payload, 41)? as f64 / 1e6,
+            last_lat: grib_i32(payload, 50)? as f64 / 1e6,
+            first_lon,
+            lon_span,
+            scan_mode,
+            row_points,
+        })
+    }
+
+    fn num_points(&self) -> usize {
+        self.row_points.iter().sum()
+    }
+
+    /// Coordinates of all points; None if La1/La2 don't select exactly Nj Gaussian latitudes
+    fn points(&self) -> Option<GridPoints> {
+        const TOLERANCE: f64 = 1e-3; // La1/La2 are rounded to micro- (or milli-) degrees
+        let (south, north) = (
+            self.first_lat.min(self.last_lat) - TOLERANCE,
+            self.first_lat.max(self.last_lat) + TOLERANCE,
+        );
+        let mut lats: Vec<f64> = gaussian_latitudes(self.n)
+            .into_iter()
+            .filter(|lat| (south..=north).contains(lat))
+            .collect();
+        if lats.len() != self.row_points.len() {
+            return None;
+        }
+        if self.scan_mode & 0x40 != 0 {
+            lats.reverse();
+        }
+
+        // On a global grid every parallel wraps, so its points divide the full circle
+        let widest = *self.row_points.iter().max()? as f64;
+        let global = (self.lon_span + 360.0 / widest - 360.0).abs() < 180.0 / widest;
+        let direction = if self.scan_mode & 0x80 == 0 {
+            1.0
+        } else {
+            -1.0
+        };
+        let (first_lon, lon_span) = (self.first_lon, self.lon_span);
+
+        let rows = lats.into_iter().zip(self.row_points.clone());
+        Some(Box::new(rows.flat_map(move |(lat, count)| {
+            let step = if global {
+                360.0 / count as f64
+            } else if count > 1 {
+                lon_span / (count - 1) as f64
+            } else {
+                0.0
+            };
+            (0..count).map(move |k| (lat as f32, (first_lon + direction * step * k as f64) as f32))
+        })))
+    }
+}
+
+/// Earth radius in metres from the shape of the earth code (table 3.2) at section octet 15
+/// Oblate shapes are approximated by the WMO standard sphere
+fn earth_radius(payload: &[u8]) -> f64 {
+    match payload.get(9) {
+        Some(0) => 6_367_470.0,
+        Some(1) => {
+            let scale = *payload.get(10).unwrap_or(&0) as i32;
+            be_u32(payload, 11)
+                .map(|value| value as f64 / 10f64.powi(scale))
+                .unwrap_or(6_371_229.0)
+        }
+        _ => 6_371_229.0,
+    }
+}
+
+/// Map projection of a projected grid, on a sphere of the given radius
+#[derive(Clone, Copy)]
+enum Projection {
+    /// Lambert conformal conic: cone constant n, scale F and central meridian (radians)
+    LambertConformal { n: f64, f: f64, lov: f64 },
+    /// Polar stereographic: hemisphere is 1 for the north pole and -1 for the south pole,
+    /// k makes the scale true at latitude LaD
+    PolarStereographic { hemisphere: f64, k: f64, lov: f64 },
+}
+
+impl Projection {
+    fn lambert_conformal(latin1: f64, latin2: f64, lov: f64) -> Self {
+        let (phi1, phi2) = (latin1.to_radians(), latin2.to_radians());
+        let t = |phi: f64| (std::f64::consts::FRAC_PI_4 + phi / 2.0).tan();
+        let n = if (latin1 - latin2).abs() < 1e-9 {
+            phi1.sin()
+        } else {
+            (phi1.cos() / phi2.cos()).ln() / (t(phi2) / t(phi1)).ln()
+        };
+        Projection::LambertConformal {
+            n,
+            f: phi1.cos() * t(phi1).powf(n) / n,
+            lov: lov.to_radians(),
+        }
+    }
+
+    fn polar_stereographic(south_pole: bool, lad: f64, lov: f64) -> Self {
+        let hemisphere = if south_pole { -1.0 } else { 1.0 };
+        Projection::PolarStereographic {
+            hemisphere,
+            k: 1.0 + (hemisphere * lad).to_radians().sin(),
+            lov: lov.to_radians(),
+        }
+    }
+
+    /// Projected (x, y) in units of the earth radius
+    fn forward(&self, lat: f64, lon: f64) -> (f64, f64) {
+        match *self {
+            Projection::LambertConformal { n, f, lov } => {
+                let rho = f
+                    / (std::f64::consts::FRAC_PI_4 + lat.to_radians() / 2.0)
+                        .tan()
+                        .powf(n);
+                let theta = n * wrap_radians(lon.to_radians() - lov);
+                (rho * theta.sin(), -rho * theta.cos())
+            }
+            Projection::PolarStereographic { hemisphere, k, lov } => {
+                let rho =
+                    k * (std::f64::consts::FRAC_PI_4 - hemisphere * lat.to_radians() / 2.0).tan();
+                let theta = lon.to_radians() - lov;
+                (rho * theta.sin(), -hemisphere * rho * theta.cos())
+            }
+        }
+    }
+
+    /// (lat, lon) in degrees of projected (x, y)
+    fn inverse(&self, x: f64, y: f64) -> (f64, f64) {
+        match *self {
+            Projection::LambertConformal { n, f, lov } => {
+                let rho = n.signum() * x.hypot(y);
+                let theta = (n.signum() * x).atan2(-n.signum() * y);
+                let lat = 2.0 * (f / rho).powf(1.0 / n).atan() - std::f64::consts::FRAC_PI_2;
+                (lat.to_degrees(), wrap_radians(lov + theta / n).to_degrees())
+            }
+            Projection::PolarStereographic { hemisphere, k, lov } => {
+                let lat =
+                    hemisphere * (std::f64::consts::FRAC_PI_2 - 2.0 * (x.hypot(y) / k).atan());
+                let theta = x.atan2(-hemisphere * y);
+                (lat.to_degrees(), wrap_radians(lov + theta).to_degrees())
+            }
+        }
+    }
+}
+
+/// Longitude in degrees, in any encoding (0..360, -180..180 or a negative Lo1),
+/// wrapped into the half-open range of the convention
+/// The antimeridian is always -180 (and the prime meridian 0 under Unsigned), so a
+/// grid crossing it has no duplicate seam column
+fn normalize_longitude(lon: f64, convention: Grib2LonConvention) -> f64 {
+    let offset = match convention {
+        Grib2LonConvention::Signed => 180.0,
+        Grib2LonConvention::Unsigned => 0.0,
+    };
+    // rem_euclid rounds tiny negative remainders up to exactly 360
+    match (lon + offset).rem_euclid(360.0) {
+        wrapped if wrapped >= 360.0 => -offset,
+        wrapped => wrapped - offset,
+    }
+}
+
+/// Angle in radians wrapped into [-pi, pi)
+fn wrap_radians(angle: f64) -> f64 {
+    use std::f64::consts::PI;
+    (angle + PI).rem_euclid(2.0 * PI) - PI
+}
+
+/// Equally spaced points of a map projection (templates 3.20 and 3.30)
+#[derive(Clone, Copy)]
+struct ProjectedGrid {
+    nx: usize,
+    ny: usize,
+    dx: f64, // grid length in units of the earth radius
+    dy: f64,
+    origin: (f64, f64), // projected position of the first grid point
+    scan_mode: u8,
+    projection: Projection,
+}
+
+impl ProjectedGrid {
+    fn from_grid_def(grid_def: &GridDefinition) -> Option<Self> {
+        let payload = grid_def.iter().as_slice();
+        let micro_degrees = |pos| Some(grib_i32(payload, pos)? as f64 / 1e6);
+        // Both templates share the layout up to the scanning mode at octet 65
+        let projection = match grid_def.grid_tmpl_num() {
+            20 => {
+                // Bit 1 of the projection centre flag selects the south pole
+                let south_pole = *payload.get(58)? & 0x80 != 0;
+                Projection::polar_stereographic(south_pole, micro_degrees(42)?, micro_degrees(46)?)
+            }
+            30 => Projection::lambert_conformal(
+                micro_degrees(60)?,
+                micro_degrees(64)?,
+                micro_degrees(46)?,
+            ),
+            _ => return None,
+        };
+
+        let nx = be_u32(payload, 25)? as usize;
+        let ny = be_u32(payload, 29)? as usize;
+        if nx == 0 || ny == 0 {
+            return None;
+        }
+        // Dx/Dy are in millimetres
+        let radius = earth_radius(payload);
+        let dx = be_u32(payload, 50)? as f64 / 1e3 / radius;
+        let dy = be_u32(payload, 54)? as f64 / 1e3 / radius;
+        let origin = projection.forward(micro_degrees(33)?, micro_degrees(37)?);
+        if !(origin.0.is_finite() && origin.1.is_finite()) {
+            return None;
+        }
+
+        Some(ProjectedGrid {
+            nx,
+            ny,
+            dx,
+            dy,
+            origin,
+            scan_mode: *payload.get(59)?,
+            projection,
+        })
+    }
+
+    fn num_points(&self) -> usize {
+        self.nx * self.ny
+    }
+
+    /// Coordinates of all points in scan order
+    fn points(self) -> GridPoints {
+        let x_step = if self.scan_mode & 0x80 == 0 {
+            self.dx
+        } else {
+            -self.dx
+        };
+        let y_step = if self.scan_mode & 0x40 != 0 {
+            self.dy
+        } else {
+            -self.dy
+        };
+        Box::new((0..self.num_points()).map(move |k| {
+            let (i, j) = if self.scan_mode & 0x20 == 0 {
+                (k % self.nx, k / self.nx)
+            } else {
+                (k / self.ny, k % self.ny)
+            };
+            let x = self.origin.0 + i as f64 * x_step;
+            let y = self.origin.1 + j as f64 * y_step;
+            let (lat, lon) = self.projection.inverse(x, y);
+            (lat as f32, lon as f32)
+        }))
+    }
+}
+
+/// South pole rotation of a rotated lat/lon grid (template 3.1), in degrees
+#[derive(Clone, Copy)]
+struct PoleRotation {
+    south_pole_lat: f64,
+    south_pole_lon: f64,
+    angle: f64, // rotation about the new polar axis
+}
+
+impl PoleRotation {
+    fn from_grid_def(grid_def: &GridDefinition) -> Option<Self> {
+        if grid_def.grid_tmpl_num() != 1 {
+            return None;
+        }
+        // Octets 73-84 follow the template 3.0 layout; the angle is an IEEE float
+        let payload = grid_def.iter().as_slice();
+        let angle = f32::from_bits(be_u32(payload, 75)?) as f64;
+        Some(PoleRotation {
+            south_pole_lat: grib_i32(payload, 67)? as f64 / 1e6,
+            south_pole_lon: grib_i32(payload, 71)? as f64 / 1e6,
+            angle: if angle.is_finite() { angle } else { 0.0 },
+        })
+    }
+
+    /// Geographic (lat, lon) of a point given in the rotated frame
+    fn unrotate(&self, lat: f64, lon: f64) -> (f64, f64) {
+        let (lat, lon) = (lat.to_radians(), (lon + self.angle).to_radians());
+        let (x, y, z) = (lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin());
+        // Tilt the rotated pole down to its geographic colatitude, then turn to its meridian
+        let tilt = -(90.0 + self.south_pole_lat).to_radians();
+        let (x, z) = (
+            tilt.cos() * x + tilt.sin() * z,
+            -tilt.sin() * x + tilt.cos() * z,
+        );
+        let lon = wrap_radians(y.atan2(x) + self.south_pole_lon.to_radians());
+        (z.clamp(-1.0, 1.0).asin().to_degrees(), lon.to_degrees())
+    }
+}
+
+/// Coordinates of a rotated lat/lon grid (template 3.1) in the geographic frame
+fn rotated_points(grid_def: &GridDefinition) -> Option<GridPoints> {
+    let rotation = PoleRotation::from_grid_def(grid_def)?;
+    let frame = LatLonGrid::from_payload(grid_def.iter().as_slice())?;
+    Some(Box::new(frame.points().map(move |(lat, lon)| {
+        let (lat, lon) = rotation.unrotate(lat, lon);
+        (lat as f32, lon as f32)
+    })))
+}
+
+/// Latitudes in degrees, north to south, of the 2n parallels of a Gaussian grid
+/// They are the roots of the Legendre polynomial of degree 2n, found by Newton iteration
+fn gaussian_latitudes(n: usize) -> Vec<f64> {
+    let degree = 2 * n;
+    let mut lats = vec![0.0; degree];
+    for i in 0..n {
+        let mut z = (std::f64::consts::PI * (i as f64 + 0.75) / (degree as f64 + 0.5)).cos();
+        for _ in 0..100 {
+            let (mut p1, mut p2) = (1.0, 0.0);
+            for j in 1..=degree {
+                let p3 = p2;
+                p2 = p1;
+                p1 = ((2 * j - 1) as f64 * z * p2 - (j - 1) as f64 * p3) / j as f64;
+            }
+            let derivative = degree as f64 * (z * p1 - p2) / (z * z - 1.0);
+            let delta = p1 / derivative;
+            z -= delta;
+            if delta.abs() < 1e-15 {
+                break;
+            }
+        }
+        let lat = z.asin().to_degrees();
+        lats[i] = lat;
+        lats[degree - 1 - i] = -lat;
+    }
+    lats
+}
+
+/// Check that the coordinates of a message can be produced, without building them
+fn check_grid<R>(submessage: &SubMessage<R>) -> Result<(), String> {
+    let grid_def = submessage.grid_def();
+    let num_points = grid_def.num_points() as usize;
+    match grid_def.grid_tmpl_num() {
+        40 => match GaussianGrid::from_grid_def(grid_def) {
+            Some(grid) if grid.num_points() == num_points => Ok(()),
+            _ => Err("Inconsistent Gaussian grid definition (template 3.40)".to_string()),
+        },
+        1 => match (
+            PoleRotation::from_grid_def(grid_def),
+            LatLonGrid::from_payload(grid_def.iter().as_slice()),
+        ) {
+            (Some(_), Some(frame)) if frame.ni * frame.nj == num_points => Ok(()),
+            _ => Err("Inconsistent rotated lat/lon grid definition (template 3.1)".to_string()),
+        },
+        template @ (20 | 30) => match ProjectedGrid::from_grid_def(grid_def) {
+            Some(grid) if grid.num_points() == num_points => Ok(()),
+            _ => Err(format!(
+                "Inconsistent projected grid definition (template 3.{})",
+                template
+            )),
+        },
+        // The crate reads the template without bounds checks and sizes its axis
+        // vectors from Ni and Nj alone, so corrupt dimensions would allocate gigabytes
+        0 => match LatLonGrid::from_payload(grid_def.iter().as_slice()) {
+            Some(grid) if grid.ni.checked_mul(grid.nj) == Some(num_points) => submessage
+                .latlons()
+                .map(|_| ())
+                .map_err(|e| format!("Unsupported grid (template 3.0): {}", e)),
+            _ => Err("Inconsistent lat/lon grid definition (template 3.0)".to_string()),
+        },
+        // Only builds the per-axis coordinate vectors, so this stays cheap
+        template => submessage
+            .latlons()
+            .map(|_| ())
+            .map_err(|e| format!("Unsupported grid (template 3.{}): {}", template, e)),
+    }
+}
+
+/// Coordinates of every grid point of a message, including grids the grib crate can't map
+fn grid_points<R>(submessage: &SubMessage<R>) -> Option<GridPoints> {
+    let grid_def = submessage.grid_def();
+    match grid_def.grid_tmpl_num() {
+        1 => rotated_points(grid_def),
+        40 => GaussianGrid::from_grid_def(grid_def)?.points(),
+        20 | 30 => Some(ProjectedGrid::from_grid_def(grid_def)?.points()),
+        _ => Some(Box::new(submessage.latlons().ok()?)),
+    }
+}
+
+/// Extent of a regular lat/lon or Gaussian grid from its first and last grid points,
+/// as (min lat, west, max lat, east) with longitudes in [-180, 180); west > east when
+/// the grid crosses the antimeridian and (-180, 180) when it circles the globe
+/// None for grids whose corners don't bound them (rotated or projected)
+fn grid_bounds(grid_def: &GridDefinition) -> Option<(f64, f64, f64, f64)> {
+    // Both latitudes, the westernmost longitude, the span east of it and the column spacing
+    let (first_lat, last_lat, west, span, step) = match grid_def.grid_tmpl_num() {
+        0 => {
+            let grid = LatLonGrid::from_grid_def(grid_def)?;
+            let span = grid.lon_step.abs() * (grid.ni - 1) as f64;
+            let west = grid
+                .first_lon
+                .min(grid.first_lon + grid.lon_step * (grid.ni - 1) as f64);
+            let last_lat = grid.first_lat + grid.lat_step * (grid.nj - 1) as f64;
+            (grid.first_lat, last_lat, west, span, grid.lon_step.abs())
+        }
+        40 => {
+            let grid = GaussianGrid::from_grid_def(grid_def)?;
+            let widest = grid.row_points.iter().copied().max()?;
+            let step = if widest > 1 {
+                grid.lon_span / (widest - 1) as f64
+            } else {
+                0.0
+            };
+            let west = if grid.scan_mode & 0x80 == 0 {
+                grid.first_lon
+            } else {
+                grid.first_lon - grid.lon_span
+            };
+            (grid.first_lat, grid.last_lat, west, grid.lon_span, step)
+        }
+        _ => return None,
+    };
+    let (min_lat, max_lat) = (first_lat.min(last_lat), first_lat.max(last_lat));
+    // One more column would close the circle: every longitude is inside
+    if step > 0.0 && span + step >= 360.0 - step / 2.0 {
+        return Some((min_lat, -180.0, max_lat, 180.0));
+    }
+    let west = normalize_longitude(west, Grib2LonConvention::Signed);
+    let east = match normalize_longitude(west + span, Grib2LonConvention::Signed) {
+        // A grid ending on the antimeridian doesn't wrap
+        -180.0 => 180.0,
+        east => east,
+    };
+    Some((min_lat, west, max_lat, east))
+}
+
+/// Outcome of a point query against a single message
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grib2QueryStatus {
+    Ok = 0,
+    InvalidMessage = 1,
+    OutsideGrid = 2,
+    UnsupportedGrid = 3,
+    DecodeFailed = 4,
+    NearestFallback = 5, // a corner was missing, value is the nearest grid point
+}
+
+/// Category of a failure to open a source, stable across releases
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grib2ErrorCode {
+    Ok = 0,
+    InvalidArgument = 1, // null or non UTF-8 path, empty buffer
+    FileNotFound = 2,
+    IoError = 3,
+    NotGrib = 4, // not GRIB data, or a GRIB edition other than 2
+    UnsupportedTemplate = 5,
+    DecodeFailed = 6, // malformed GRIB2, or a corrupt compressed stream
+    OutOfBounds = 7,  // requested byte range lies outside the source
+    HttpError = 8,
+    Internal = 9,   // a bug caught at the FFI boundary
+    Truncated = 10, // a message runs past the end of the data, e.g. a cut-off download
+    Empty = 11,     // no GRIB messages at all, e.g. a zero-byte file
+    NotFound = 12,  // no message matched, see grib2_open_first_match
+}
+
+/// Error message together with its C-facing category
+#[derive(Debug)]
+pub struct Grib2Error {
+    code: Grib2ErrorCode,
+    message: String,
+}
+
+impl Grib2Error {
+    fn new(code: Grib2ErrorCode, message: impl Into<String>) -> Self {
+        Grib2Error {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn io(context: &str, e: std::io::Error) -> Self {
+        let code = match e.kind() {
+            std::io::ErrorKind::NotFound => Grib2ErrorCode::FileNotFound,
+            std::io::ErrorKind::UnexpectedEof => Grib2ErrorCode::DecodeFailed,
+            _ => Grib2ErrorCode::IoError,
+        };
+        Grib2Error::new(code, format!("{}: {}", context, e))
+    }
+
+    /// Category of the failure, as the FFI reports it in error_code
+    pub fn code(&self) -> Grib2ErrorCode {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for Grib2Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Grib2Error {}
+
+impl From<grib::GribError> for Grib2Error {
+    fn from(e: grib::GribError) -> Self {
+        use grib::{GribError, ParseError};
+        let code = match &e {
+            GribError::ParseError(ParseError::NotGRIB | ParseError::GRIBVersionMismatch(_)) => {
+                Grib2ErrorCode::NotGrib
+            }
+            GribError::NotSupported(_) => Grib2ErrorCode::UnsupportedTemplate,
+            _ => Grib2ErrorCode::DecodeFailed,
+        };
+        Grib2Error::new(code, format!("Failed to parse GRIB: {}", e))
+    }
+}
+
+/// Read a sign-magnitude GRIB integer (top bit is the sign)
+fn grib_i32(buf: &[u8], pos: usize) -> Option<i32> {
+    let raw = be_u32(buf, pos)?;
+    let magnitude = (raw & 0x7fff_ffff) as i32;
+    Some(if raw & 0x8000_0000 != 0 { -magnitude } else { magnitude })
+}
+
+/// Read a big-endian u32 from a section payload
+fn be_u32(buf: &[u8], pos: usize) -> Option<u32> {
+    let bytes = buf.get(pos..pos + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Split unix seconds into a UTC calendar date and time (proleptic Gregorian)
+fn civil_time(seconds: i64) -> (u16, u8, u8, u8, u8, u8) {
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+    // Inverse of days_from_civil, counting 400-year eras from 0000-03-01
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (
+        year.clamp(0, 65_535) as u16,
+        month as u8,
+        day as u8,
+        (time / 3600) as u8,
+        (time % 3600 / 60) as u8,
+        (time % 60) as u8,
+    )
+}
+
+/// Unix seconds of a UTC calendar date and time, the inverse of civil_time
+fn unix_time(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> i64 {
+    // days_from_civil: years start in March so the leap day comes last
+    let (month, day) = (i64::from(month), i64::from(day));
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    days * 86_400 + i64::from(hour) * 3600 + i64::from(minute) * 60 + i64::from(second)
+}
+
+/// Unix seconds as an ISO 8601 UTC timestamp
+fn iso_time(seconds: i64) -> String {
+    let (year, month, day, hour, minute, second) = civil_time(seconds);
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z")
+}
+
+/// Great-circle distance between two coordinates in kilometres
+fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (dlat, dlon) = ((lat2 - lat1).to_radians(), (lon2 - lon1).to_radians());
+    let a = (dlat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (dlon / 2.0).sin().powi(2);
+    6371.0 * 2.0 * a.sqrt().asin()
+}
+
+/// Physical value of a fixed surface: scaled value * 10^-scale factor
+/// A missing scale factor (all bits set) is read as 0 instead of scaling by 10^127
+fn surface_value(surface: &FixedSurface) -> f64 {
+    if surface.scale_factor_is_nan() && !surface.value_is_nan() {
+        f64::from(surface.scaled_value)
+    } else {
+        surface.value()
+    }
+}
+
+/// Length of a code table 4.4 time unit in seconds; None for calendar units (months, years, ...)
+fn unit_seconds(unit: &Table4_4) -> Option<i64> {
+    match unit {
+        Table4_4::Second => Some(1),
+        Table4_4::Minute => Some(60),
+        Table4_4::Hour => Some(3_600),
+        Table4_4::ThreeHours => Some(10_800),
+        Table4_4::SixHours => Some(21_600),
+        Table4_4::TwelveHours => Some(43_200),
+        Table4_4::Day => Some(86_400),
+        _ => None,
+    }
+}
+
+/// Forecast time in seconds; None for calendar units or code 255
+fn lead_seconds(forecast_time: &ForecastTime) -> Option<i64> {
+    match &forecast_time.unit {
+        Name(unit) => unit_seconds(unit).map(|s| i64::from(forecast_time.value) * s),
+        Num(_) => None,
+    }
+}
+
+/// Statistical process (code table 4.10) and length of its time range in seconds
+/// Templates 4.8-4.12 describe accumulations, averages, extremes, ... over an
+/// interval ending at the valid time; instantaneous fields give (255, 0) and
+/// ranges in calendar units (e.g. monthly means) a length of -1
+fn statistical_info(prod_def: &ProdDefinition) -> (u8, i64) {
+    let Some(interval) = interval_block(prod_def) else {
+        return (255, 0);
+    };
+    // First (outermost) time range specification
+    let (process, unit, length) = match interval.get(12..19) {
+        Some(spec) => (
+            spec[0],
+            spec[2],
+            u32::from_be_bytes([spec[3], spec[4], spec[5], spec[6]]),
+        ),
+        None => return (255, 0),
+    };
+    let seconds = Table4_4::try_from(unit)
+        .ok()
+        .and_then(|unit| unit_seconds(&unit))
+        .map_or(-1, |s| i64::from(length) * s);
+    (process, seconds)
+}
+
+/// Templates 4.8-4.12 from the octet where the interval block (end of interval,
+/// number of ranges, ...) starts; None for other templates
+fn interval_block(prod_def: &ProdDefinition) -> Option<&[u8]> {
+    let start = match prod_def.prod_tmpl_num() {
+        8 => 35,
+        9 => 48,
+        10 => 36,
+        11 => 38,
+        12 => 37,
+        _ => return None,
+    };
+    prod_def.iter().as_slice().get(start - 6..)
+}
+
+/// Time the field is valid at in unix seconds, 0 when unknown like reference_time
+/// Statistically processed fields are valid at the end of their interval, which
+/// the template states outright; others at the reference time plus the lead time
+fn valid_time(prod_def: &ProdDefinition, reference_time: i64, lead_seconds: Option<i64>) -> i64 {
+    if reference_time == 0 {
+        return 0;
+    }
+    if let Some(end) = interval_block(prod_def).and_then(|b| b.get(..7)) {
+        let year = u16::from_be_bytes([end[0], end[1]]);
+        return unix_time(year, end[2], end[3], end[4], end[5], end[6]);
+    }
+    lead_seconds.map_or(0, |lead| reference_time + lead)
+}
+
+/// Classify a message from its product template, type of generating process
+/// (code table 4.3) and reference time significance (code table 1.2)
+fn message_kind(prod_def: &ProdDefinition, reference_time_significance: u8) -> Grib2MessageKind {
+    let template = prod_def.prod_tmpl_num();
+    let process = prod_def.generating_process();
+    match template {
+        5 | 9 => return Grib2MessageKind::Probability,
+        // Type of ensemble forecast (code table 4.6) in octet 35: 0/1 are controls
+        1 | 11 => {
+            return match prod_def.iter().as_slice().get(29) {
+                Some(0 | 1) => Grib2MessageKind::EnsembleControl,
+                Some(2..=4) => Grib2MessageKind::EnsemblePerturbed,
+                _ => Grib2MessageKind::Unknown,
+            }
+        }
+        _ => {}
+    }
+    match (process, reference_time_significance) {
+        (Some(9), _) => Grib2MessageKind::Climatology,
+        (Some(5), _) => Grib2MessageKind::Probability,
+        (Some(0 | 7 | 8), _) | (_, 0 | 3) => Grib2MessageKind::Analysis,
+        (Some(1..=4 | 6 | 10..=12), _) | (_, 1 | 2) => Grib2MessageKind::Forecast,
+        _ => Grib2MessageKind::Unknown,
+    }
+}
+
+/// Probability type (code table 4.9) and lower/upper limits of templates 4.5/4.9
+/// e.g. type 1 with an upper limit of 1 is P(value > 1); other templates give
+/// (-1, NaN, NaN), and a missing limit is NaN
+fn probability_info(prod_def: &ProdDefinition) -> (i32, f64, f64) {
+    if !matches!(prod_def.prod_tmpl_num(), 5 | 9) {
+        return (-1, f64::NAN, f64::NAN);
+    }
+    let payload = prod_def.iter().as_slice();
+    // The type sits in octet 37, each limit is a sign-magnitude scale factor octet
+    // followed by a 4-octet scaled value
+    let limit = |pos: usize| {
+        let factor = *payload.get(pos)?;
+        if factor == 255 && be_u32(payload, pos + 1)? == u32::MAX {
+            return None;
+        }
+        let exponent = match factor {
+            f if f & 0x80 != 0 => -i32::from(f & 0x7f),
+            f => i32::from(f),
+        };
+        Some(f64::from(grib_i32(payload, pos + 1)?) / 10f64.powi(exponent))
+    };
+    match payload.get(31) {
+        Some(&kind) if kind != 255 => (
+            i32::from(kind),
+            limit(32).unwrap_or(f64::NAN),
+            limit(37).unwrap_or(f64::NAN),
+        ),
+        _ => (-1, f64::NAN, f64::NAN),
+    }
+}
+
+/// Percentile value (0-100) of templates 4.6/4.10, in octet 35; -1 otherwise
+fn percentile(prod_def: &ProdDefinition) -> i32 {
+    match prod_def.prod_tmpl_num() {
+        6 | 10 => match prod_def.iter().as_slice().get(29) {
+            Some(&value) if value <= 100 => i32::from(value),
+            _ => -1,
+        },
+        _ => -1,
+    }
+}
+
+/// Analysis or forecast generating process identifier, the centre's own number for
+/// the model that produced the field (e.g. 96 for GFS at NCEP); octet 14 in
+/// templates 4.0-4.15, 255 when missing or for other templates
+fn generating_process(prod_def: &ProdDefinition) -> u8 {
+    match prod_def.prod_tmpl_num() {
+        0..=15 => prod_def.iter().as_slice().get(8).copied().unwrap_or(255),
+        _ => 255,
+    }
+}
+
+/// Section 4 payload octets the grib crate's forecast_time() and fixed_surfaces()
+/// read for a product template: both slice the payload without bounds checks, so a
+/// shorter section would panic. The offsets mirror the crate's own tables (forecast
+/// time unit, then the first of the two 6-octet surfaces), after the 4 octets ahead
+/// of the template; 0 for templates they don't read
+fn prod_def_len_read(template: u16) -> usize {
+    let (unit, surface) = match template {
+        0..=15 | 51 | 60 | 61 | 86 | 87 | 91 | 1100 | 1101 => (8, Some(13)),
+        32..=34 | 1000..=1099 => (8, None),
+        40..=43 => (10, Some(15)),
+        44 => (21, Some(24)),
+        45..=47 | 85 => (21, Some(26)),
+        48 | 49 => (32, Some(37)),
+        55 | 56 | 59 | 62 | 63 => (14, Some(19)),
+        70..=73 => (13, Some(18)),
+        76..=79 => (11, Some(16)),
+        80 | 81 => (33, Some(38)),
+        82..=84 => (22, Some(27)),
+        88 => (26, Some(5)),
+        _ => return 0,
+    };
+    4 + (unit + 5).max(surface.map_or(0, |s| s + 12))
+}
+
+/// Data representation template number and bits per packed value from section 5
+/// 5.0-5.3 and 5.40-5.61 give the width in octet 20, run length packing (5.200) in
+/// octet 12; other templates report 255 bits
+fn packing_info(repr_def: &ReprDefinition) -> (u16, u8) {
+    let template = repr_def.repr_tmpl_num();
+    let offset = match template {
+        0..=3 | 40..=42 | 50 | 51 | 61 => 14,
+        200 => 6,
+        _ => return (template, 255),
+    };
+    let bits = repr_def.iter().as_slice().get(offset).copied();
+    (template, bits.unwrap_or(255))
+}
+
+/// Significant bits each value keeps: the packed width of the templates that scale
+/// integers, 0 for a constant field, 24/53/113 for IEEE single/double/quad (5.4,
+/// octet 12); 255 when unknown
+fn precision_bits(repr_def: &ReprDefinition, bits_per_value: u8) -> u8 {
+    match repr_def.repr_tmpl_num() {
+        0..=3 | 40..=42 | 50 | 51 | 61 => bits_per_value,
+        4 => match repr_def.iter().as_slice().get(6) {
+            Some(1) => 24,
+            Some(2) => 53,
+            Some(3) => 113,
+            _ => 255,
+        },
+        _ => 255,
+    }
+}
+
+/// Whether a message packs no bits per value, so every point holds the reference
+/// value; only for templates where that is how a constant field is coded
+fn constant_field(msg: &ParsedMessage) -> bool {
+    matches!(msg.packing_template, 0..=3 | 40..=42) && msg.bits_per_value == 0
+}
+
+/// Packed integer X behind a decoded value, inverting value = (R + X * 2^E) / 10^D
+/// Without a scale the value itself is rounded; missing values give 0
+fn packed_integer(scale: Option<(f32, i16, i16)>, value: f64) -> i64 {
+    if value.is_nan() {
+        return 0;
+    }
+    let raw = match scale {
+        Some((reference, binary, decimal)) => {
+            (value * 10f64.powi(decimal.into()) - reference as f64) / 2f64.powi(binary.into())
+        }
+        None => value,
+    };
+    raw.round() as i64
+}
+
+/// Reference value R, binary scale factor E and decimal scale factor D of the
+/// templates that pack Y = (R + X * 2^E) / 10^D, None for the others (e.g. 5.200)
+fn packing_scale(repr_def: &ReprDefinition) -> Option<(f32, i16, i16)> {
+    if !matches!(repr_def.repr_tmpl_num(), 0..=3 | 40..=42 | 50 | 51 | 61) {
+        return None;
+    }
+    // Scale factors are sign and magnitude
+    let payload = repr_def.iter().as_slice();
+    let reference = f32::from_be_bytes(payload.get(6..10)?.try_into().ok()?);
+    let scale = |pos: usize| {
+        let raw = u16::from_be_bytes(payload.get(pos..pos + 2)?.try_into().ok()?);
+        let magnitude = (raw & 0x7fff) as i16;
+        Some(if raw & 0x8000 != 0 {
+            -magnitude
+        } else {
+            magnitude
+        })
+    };
+    Some((reference, scale(10)?, scale(12)?))
+}
+
+/// Perturbation number and number of forecasts in the ensemble, -1 when absent
+/// Individual members (templates 4.1/4.11) carry both in octets 36-37; derived
+/// forecasts over all members (4.2/4.12) only give the count, in octet 36
+fn ensemble_info(prod_def: &ProdDefinition) -> (i32, i32) {
+    let payload = prod_def.iter().as_slice();
+    // 255 means missing
+    let octet = |pos: usize| match payload.get(pos) {
+        Some(&v) if v != 255 => i32::from(v),
+        _ => -1,
+    };
+    match prod_def.prod_tmpl_num() {
+        1 | 11 => (octet(30), octet(31)),
+        2 | 12 => (-1, octet(30)),
+        _ => (-1, -1),
+    }
+}
+
+/// HTTP GET of `length` bytes starting at `offset`; a zero length reads to the end
+/// Failures are prefixed "HTTP error" so they can be told apart from parse failures
+#[cfg(feature = "remote")]
+fn fetch_range(url: &str, offset: u64, length: u64) -> Result<Vec<u8>, Grib2Error> {
+    let ranged = offset > 0 || length > 0;
+    let mut request = ureq::get(url);
+    if ranged {
+        let range = if length > 0 {
+            format!("bytes={}-{}", offset, offset.saturating_add(length - 1))
+        } else {
+            format!("bytes={}-", offset)
+        };
+        request = request.set("Range", &range);
+    }
+
+    let response = request.call().map_err(|e| {
+        let code = match &e {
+            ureq::Error::Status(404, _) => Grib2ErrorCode::FileNotFound,
+            ureq::Error::Status(416, _) => Grib2ErrorCode::OutOfBounds,
+            _ => Grib2ErrorCode::HttpError,
+        };
+        Grib2Error::new(code, format!("HTTP error: {}", e))
+    })?;
+    let partial = response.status() == 206;
+    let mut body = Vec::new();
+    response.into_reader().read_to_end(&mut body).map_err(|e| {
+        Grib2Error::new(
+            Grib2ErrorCode::HttpError,
+            format!("HTTP error: failed to read body: {}", e),
+        )
+    })?;
+
+    // A server that ignores Range answers 200 with the whole file
+    if ranged && !partial {
+        let start = usize::try_from(offset).unwrap_or(usize::MAX);
+        if start >= body.len() {
+            return Err(Grib2Error::new(
+                Grib2ErrorCode::OutOfBounds,
+                format!("HTTP error: offset {} is past the end of the file", offset),
+            ));
+        }
+        let end = match usize::try_from(length) {
+            Ok(0) => body.len(),
+            Ok(len) => start.saturating_add(len).min(body.len()),
+            Err(_) => body.len(),
+        };
+        body = body[start..end].to_vec();
+    }
+    if body.is_empty() {
+        return Err(Grib2Error::new(
+            Grib2ErrorCode::HttpError,
+            "HTTP error: empty response body",
+        ));
+    }
+    Ok(body)
+}
+
+/// The packed values of one message: unpacked by the grib crate, or by the complex
+/// module for complex packing
+enum Unpacker {
+    Decoder(Grib2SubmessageDecoder),
+    Complex(complex::ComplexPacked),
+    Constant {
+        value: f32,
+        bitmap: Option<Vec<u8>>, // section 6 bits after the indicator, if present
+        num_points: usize,
+    },
+}
+
+impl Unpacker {
+    /// Values in scan order; the crate's decoder unpacks them as they are pulled
+    fn values(&self) -> Result<Box<dyn Iterator<Item = f32> + Send + '_>, SkipCause> {
+        match self {
+            Unpacker::Decoder(decoder) => Ok(Box::new(decoder.dispatch().map_err(unpack_error)?)),
+            Unpacker::Complex(packed) => {
+                let values = packed.unpack().map_err(|e| {
+                    (
+                        Grib2SkipReason::DecodeFailed,
+                        format!("Failed to unpack values: {e}"),
+                    )
+                })?;
+                Ok(Box::new(values.into_iter()))
+            }
+            &Unpacker::Constant {
+                value,
+                ref bitmap,
+                num_points,
+            } => Ok(Box::new((0..num_points).map(move |i| match bitmap {
+                Some(bits) if bits.get(i / 8).is_none_or(|b| b & (0x80 >> (i % 8)) == 0) => {
+                    f32::NAN
+                }
+                _ => value,
+            }))),
+        }
+    }
+}
+
+/// Sniff the leading magic bytes and hand compressed input to the parser decompressed
+/// Compressed streams can't seek, so they are inflated into memory first
+fn decompressed<R: Read + Seek + Send + 'static>(
+    mut reader: R,
+) -> Result<Box<dyn GribSource>, Grib2Error> {
+    let start = reader
+        .stream_position()
+        .map_err(|e| Grib2Error::io("Failed to read input", e))?;
+    let mut magic = Vec::with_capacity(4);
+    (&mut reader)
+        .take(4)
+        .read_to_end(&mut magic)
+        .map_err(|e| Grib2Error::io("Failed to read input", e))?;
+    reader
+        .seek(SeekFrom::Start(start))
+        .map_err(|e| Grib2Error::io("Failed to read input", e))?;
+
+    // Empty input or a cut-off magic is left for the parser to report
+    if b"GRIB".starts_with(&magic) {
+        Ok(Box::new(reader))
+    } else if magic.starts_with(&[0x1f, 0x8b]) {
+        inflate_gzip(reader)
+    } else if magic.starts_with(b"BZh") {
+        inflate_bzip2(reader)
+    } else {
+        Err(Grib2Error::new(
+            Grib2ErrorCode::NotGrib,
+            "Failed to parse GRIB: Not GRIB data",
+        ))
+    }
+}
+
+/// Walk the section 0 headers and fail with Truncated when a message runs past the
+/// end of the data or lacks its "7777" terminator, as after a cut-off download,
+/// or with NotGrib naming the edition when a GRIB1 message turns up
+/// Sections that overrun their message or come out of order fail with DecodeFailed
+/// Data holding only messages without any sections fails with Empty
+/// Bytes that don't start a GRIB2 message end the walk and are left to the parser
+fn check_complete(source: &mut dyn GribSource) -> Result<(), Grib2Error> {
+    let io = |e| Grib2Error::io("Failed to read input", e);
+    let start = source.stream_position().map_err(io)?;
+    let end = source.seek(SeekFrom::End(0)).map_err(io)?;
+    let truncated = |offset: u64, complete: usize, detail: String| {
+        Grib2Error::new(
+            Grib2ErrorCode::Truncated,
+            format!(
+                "Truncated GRIB2 data at byte {}: {}; {} complete message(s) before it",
+                offset, detail, complete
+            ),
+        )
+    };
+
+    let mut offset = start;
+    let mut complete = 0;
+    let mut hollow = 0; // section 0 followed directly by "7777"
+    while offset < end {
+        let mut header = [0u8; 16];
+        let available = (end - offset).min(16) as usize;
+        source.seek(SeekFrom::Start(offset)).map_err(io)?;
+        source.read_exact(&mut header[..available]).map_err(io)?;
+        let magic = available.min(4);
+        if header[..magic] != b"GRIB"[..magic] {
+            break;
+        }
+        // GRIB1 keeps the edition in octet 8 too, after a 3-octet message length
+        if available >= 8 && header[7] == 1 {
+            return Err(Grib2Error::new(
+                Grib2ErrorCode::NotGrib,
+                format!(
+                    "GRIB edition 1 message at byte {offset}: only GRIB2 is supported \
+                     (ecCodes converts it: grib_set -s edition=2 in.grib1 out.grib2)"
+                ),
+            ));
+        }
+        if available < 16 {
+            return Err(truncated(
+                offset,
+                complete,
+                "incomplete section 0".to_string(),
+            ));
+        }
+        if header[7] != 2 {
+            break;
+        }
+        let length = u64::from_be_bytes(header[8..16].try_into().unwrap());
+        if length < 20 {
+            return Err(Grib2Error::new(
+                Grib2ErrorCode::DecodeFailed,
+                format!("Malformed GRIB2 data at byte {offset}: message length {length} is shorter than sections 0 and 8"),
+            ));
+        }
+        if length > end - offset {
+            let detail = format!(
+                "message declares {} bytes but only {} remain",
+                length,
+                end - offset
+            );
+            return Err(truncated(offset, complete, detail));
+        }
+        let mut terminator = [0u8; 4];
+        source
+            .seek(SeekFrom::Start(offset + length - 4))
+            .map_err(io)?;
+        source.read_exact(&mut terminator).map_err(io)?;
+        if &terminator != b"7777" {
+            return Err(truncated(
+                offset,
+                complete,
+                "missing 7777 terminator".to_string(),
+            ));
+        }
+        if let Err((at, detail)) = check_sections(source, offset + 16, offset + length - 4) {
+            return Err(Grib2Error::new(
+                Grib2ErrorCode::DecodeFailed,
+                format!("Malformed GRIB2 data at byte {at}: {detail}"),
+            ));
+        }
+        if length == 20 {
+            hollow += 1;
+        } else {
+            complete += 1;
+        }
+        offset += length;
+    }
+    if complete == 0 && hollow > 0 {
+        return Err(Grib2Error::new(
+            Grib2ErrorCode::Empty,
+            format!("No GRIB2 fields: {hollow} message(s) without any sections"),
+        ));
+    }
+
+    source.seek(SeekFrom::Start(start)).map_err(io)?;
+    Ok(())
+}
+
+/// Find the next GRIB2 message from `(message_index, offset)` on holding a field whose
+/// discipline (section 0) and parameter (section 4, octets 10-11) pass `filter`,
+/// reading only section headers; returns its index, offset and length
+fn next_match(
+    source: &mut (impl Read + Seek),
+    (mut message_index, mut offset): (u32, u64),
+    filter: &MessageFilter,
+) -> Result<Option<(u32, u64, u64)>, Grib2Error> {
+    let io = |e| Grib2Error::io("Failed to read file", e);
+    let malformed = |at: u64, detail: &str| {
+        Grib2Error::new(
+            Grib2ErrorCode::DecodeFailed,
+            format!("Malformed GRIB2 data at byte {at}: {detail}"),
+        )
+    };
+    let end = source.seek(SeekFrom::End(0)).map_err(io)?;
+    while end.saturating_sub(offset) >= 16 {
+        let mut header = [0u8; 16];
+        source.seek(SeekFrom::Start(offset)).map_err(io)?;
+        source.read_exact(&mut header).map_err(io)?;
+        // Trailing data that isn't GRIB2 ends the file, as at open
+        if &header[..4] != b"GRIB" || header[7] != 2 {
+            break;
+        }
+        let length = u64::from_be_bytes(header[8..16].try_into().unwrap());
+        if length < 20 {
+            return Err(malformed(offset, "message shorter than sections 0 and 8"));
+        }
+        if length > end - offset {
+            return Err(Grib2Error::new(
+                Grib2ErrorCode::Truncated,
+                format!(
+                    "Truncated GRIB2 data at byte {offset}: message declares {length} bytes \
+                     but only {} remain",
+                    end - offset
+                ),
+            ));
+        }
+        let discipline = header[6];
+        let mut at = offset + 16;
+        while at + 5 <= offset + length - 4 {
+            let mut head = [0u8; 11];
+            source.seek(SeekFrom::Start(at)).map_err(io)?;
+            source.read_exact(&mut head[..5]).map_err(io)?;
+            let size = u32::from_be_bytes(head[..4].try_into().unwrap()) as u64;
+            if size < 5 || size > offset + length - 4 - at {
+                return Err(malformed(at, "section length runs past the message"));
+            }
+            if head[4] == 4 && size >= 11 {
+                source.read_exact(&mut head[5..]).map_err(io)?;
+                // Octets 8-9 are the template number, 10-11 the category and number; for
+                // templates the grib crate doesn't know, from_reader has the final say
+                let template = u16::from_be_bytes([head[7], head[8]]);
+                let (category, number) = field_parameter(template, Some(head[9]), Some(head[10]));
+                if filter.matches(discipline, category, number) {
+                    return Ok(Some((message_index, offset, length)));
+                }
+            }
+            at += size;
+        }
+        message_index += 1;
+        offset += length;
+    }
+    if offset == 0 {
+        return Err(if end == 0 {
+            Grib2Error::new(Grib2ErrorCode::Empty, "No GRIB2 messages in the data")
+        } else {
+            Grib2Error::new(Grib2ErrorCode::NotGrib, "Not GRIB2 data")
+        });
+    }
+    Ok(None)
+}
+
+/// Walk the sections between section 0 and "7777" of one message, checking each
+/// declared length stays inside the message and the numbers follow the order of
+/// FM 92: 1, optionally 2, then 3-7, repeating from 2, 3 or 4 for further fields
+/// The parser trusts both, allocating whatever a section declares
+/// Returns the offending offset and what is wrong there
+fn check_sections(source: &mut dyn GribSource, start: u64, end: u64) -> Result<(), (u64, String)> {
+    let mut offset = start;
+    let mut previous = 0;
+    while offset < end {
+        let mut head = [0u8; 5];
+        source
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| source.read_exact(&mut head))
+            .map_err(|e| (offset, e.to_string()))?;
+        let size = u64::from(u32::from_be_bytes(head[..4].try_into().unwrap()));
+        let number = head[4];
+        if size < 5 || size > end - offset {
+            let detail = format!(
+                "section {number} declares {size} bytes but {} remain in its message",
+                end - offset
+            );
+            return Err((offset, detail));
+        }
+        let in_order = match previous {
+            0 => number == 1,
+            1 => matches!(number, 2 | 3),
+            7 => matches!(number, 2..=4),
+            _ => number == previous + 1,
+        };
+        if !in_order {
+            return Err((
+                offset,
+                format!("section {number} follows section {previous}"),
+            ));
+        }
+        previous = number;
+        offset += size;
+    }
+    match previous {
+        0 | 7 => Ok(()),
+        _ => Err((offset, format!("message ends after section {previous}"))),
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn inflate_gzip<R: Read>(reader: R) -> Result<Box<dyn GribSource>, Grib2Error> {
+    let mut data = Vec::new();
+    flate2::read::MultiGzDecoder::new(reader)
+        .read_to_end(&mut data)
+        .map_err(|e| {
+            Grib2Error::new(
+                Grib2ErrorCode::DecodeFailed,
+                format!("Failed to decompress gzip input: {}", e),
+            )
+        })?;
+    Ok(Box::new(Cursor::new(data)))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn inflate_gzip<R: Read>(_reader: R) -> Result<Box<dyn GribSource>, Grib2Error> {
+    Err(Grib2Error::new(
+        Grib2ErrorCode::UnsupportedTemplate,
+        "Input is gzip-compressed; build with the `gzip` feature to read it",
+    ))
+}
+
+#[cfg(feature = "bzip2")]
+fn inflate_bzip2<R: Read>(reader: R) -> Result<Box<dyn GribSource>, Grib2Error> {
+    let mut data = Vec::new();
+    bzip2::read::MultiBzDecoder::new(reader)
+        .read_to_end(&mut data)
+        .map_err(|e| {
+            Grib2Error::new(
+                Grib2ErrorCode::DecodeFailed,
+                format!("Failed to decompress bzip2 input: {}", e),
+            )
+        })?;
+    Ok(Box::new(Cursor::new(data)))
+}
+
+#[cfg(not(feature = "bzip2"))]
+fn inflate_bzip2<R: Read>(_reader: R) -> Result<Box<dyn GribSource>, Grib2Error> {
+    Err(Grib2Error::new(
+        Grib2ErrorCode::UnsupportedTemplate,
+        "Input is bzip2-compressed; build with the `bzip2` feature to read it",
+    ))
+}
+
+/// H3 cell containing a point at the given resolution, 0 for non-finite coordinates
+#[cfg(feature = "h3")]
+fn h3_cell(lat: f64, lon: f64, resolution: h3o::Resolution) -> u64 {
+    h3o::LatLng::new(lat, lon)
+        .map(|ll| u64::from(ll.to_cell(resolution)))
+        .unwrap_or(0)
+}
+
+/// Identification section (section 1) shared by all submessages of a message
+fn identification<'a, R>(submessage: &SubMessage<'a, R>) -> Option<&'a Identification> {
+    match submessage.1.body.body.as_ref()? {
+        SectionBody::Section1(id) => Some(id),
+        _ => None,
+    }
+}
+
+impl Grib2Reader {
+    /// Index the messages without decoding any values
+    fn from_reader<R: Read + Seek + Send + 'static>(
+        reader: R,
+        filter: &MessageFilter,
+    ) -> Result<Self, Grib2Error> {
+        let mut source = decompressed(reader)?;
+        check_complete(source.as_mut())?;
+        let source = SharedSource::new(source);
+        let grib2 = grib::from_reader(source.clone())?;
+
+        let mut messages = Vec::new();
+        let mut skipped = Vec::new();
+        let mut submessage_counts: Vec<u32> = Vec::new();
+
+        for (submessage_pos, (msg_idx, submessage)) in grib2.iter().enumerate() {
+            if submessage_counts.len() <= msg_idx.0 {
+                submessage_counts.resize(msg_idx.0 + 1, 0);
+            }
+            submessage_counts[msg_idx.0] += 1;
+            let discipline = submessage.indicator().discipline;
+            let prod_def = submessage.prod_def();
+
+            let (category, number) = field_parameter(
+                prod_def.prod_tmpl_num(),
+                prod_def.parameter_category(),
+                prod_def.parameter_number(),
+            );
+            let param_cat = category.unwrap_or(0);
+            let param_num = number.unwrap_or(0);
+
+            log_event!(
+                Debug,
+                "message {}.{}: discipline {}, parameter {}.{}, templates 3.{} 4.{} 5.{}",
+                msg_idx.0,
+                msg_idx.1,
+                discipline,
+                param_cat,
+                param_num,
+                submessage.grid_def().grid_tmpl_num(),
+                prod_def.prod_tmpl_num(),
+                submessage.repr_def().repr_tmpl_num()
+            );
+
+            // Skip before latlons()/dispatch() so unwanted messages cost nothing
+            if !filter.matches(discipline, category, number) {
+                log_event!(
+                    Debug,
+                    "message {}.{}: not selected by the parameter filter",
+                    msg_idx.0,
+                    msg_idx.1
+                );
+                continue;
+            }
+
+            let template = prod_def.prod_tmpl_num();
+            if prod_def.iter().as_slice().len() < prod_def_len_read(template) {
+                let error = format!("Section 4 is too short for template 4.{template}");
+                log_event!(
+                    Warn,
+                    "message {}.{} skipped: {}",
+                    msg_idx.0,
+                    msg_idx.1,
+                    error
+                );
+                skipped.push(SkippedMessage {
+                    source_index: 0,
+                    message_index: msg_idx.0 as u32,
+                    submessage_index: msg_idx.1 as u32,
+                    reason: Grib2SkipReason::DecodeFailed,
+                    error,
+                });
+                continue;
+            }
+
+            let lead_time = prod_def.forecast_time();
+            let forecast_time = lead_time.as_ref().map(|ft| ft.value as i64).unwrap_or(0);
+
+            let reference_time = identification(&submessage)
+                .and_then(|id| id.ref_time().ok())
+                .map_or(0, |t| t.timestamp());
+            let identification = identification(&submessage)
+                .map_or(Grib2Identification::MISSING, Grib2Identification::new);
+
+            let lead_seconds = lead_time.as_ref().and_then(lead_seconds);
+            if !filter.matches_time(reference_time, lead_seconds) {
+                log_event!(
+                    Debug,
+                    "message {}.{}: outside the time filter",
+                    msg_idx.0,
+                    msg_idx.1
+                );
+                continue;
+            }
+
+            let ((surface_type, surface_value), (surface_type_2, surface_value_2)) = prod_def
+                .fixed_surfaces()
+                .map(|(first, second)| {
+                    // Type 255 means "missing": a single level rather than a layer
+                    let second = match second.surface_type {
+                        255 => (255, 0.0),
+                        t => (t, surface_value(&second)),
+                    };
+                    ((first.surface_type, surface_value(&first)), second)
+                })
+                .unwrap_or(((0, 0.0), (255, 0.0)));
+
+            let (ensemble_member, ensemble_size) = ensemble_info(prod_def);
+            let (packing_template, bits_per_value) = packing_info(submessage.repr_def());
+            let precision_bits = precision_bits(submessage.repr_def(), bits_per_value);
+            let packing_scale = packing_scale(submessage.repr_def());
+            let (statistical_process, time_range_seconds) = statistical_info(prod_def);
+            let (probability_type, threshold_lower, threshold_upper) = probability_info(prod_def);
+
+            if let Err(error) = check_grid(&submessage) {
+                log_event!(
+                    Warn,
+                    "message {}.{} skipped: {}",
+                    msg_idx.0,
+                    msg_idx.1,
+                    error
+                );
+                skipped.push(SkippedMessage {
+                    source_index: 0,
+                    message_index: msg_idx.0 as u32,
+                    submessage_index: msg_idx.1 as u32,
+                    reason: Grib2SkipReason::UnsupportedGrid,
+                    error,
+                });
+                continue;
+            }
+
+            messages.push(ParsedMessage {
+                file: 0,
+                submessage: submessage_pos,
+                discipline,
+                parameter_category: param_cat,
+                parameter_number: param_num,
+                forecast_time,
+                reference_time,
+                valid_time: valid_time(prod_def, reference_time, lead_seconds),
+                identification,
+                generating_process: generating_process(prod_def),
+                packing_template,
+                bits_per_value,
+                precision_bits,
+                packing_scale,
+                surface_type,
+                surface_value,
+                surface_type_2,
+                surface_value_2,
+                message_index: msg_idx.0 as u32,
+                submessage_index: msg_idx.1 as u32,
+                submessage_count: 0, // known once the whole message has been walked
+                extent: (
+                    submessage.0.body.offset as u64,
+                    submessage.indicator().total_length,
+                ),
+                source_index: 0,
+                ensemble_member,
+                ensemble_size,
+                statistical_process,
+                time_range_seconds,
+                kind: message_kind(prod_def, identification.reference_time_significance),
+                probability_type,
+                threshold_lower,
+                threshold_upper,
+                percentile: percentile(prod_def),
+                grid: GridInfo::from_grid_def(submessage.grid_def()),
+                grid_hash: GridInfo::definition_hash(submessage.grid_def()),
+                latlon_grid: LatLonGrid::from_grid_def(submessage.grid_def()),
+                bounds: grid_bounds(submessage.grid_def()),
+                num_points: submessage.grid_def().num_points() as usize,
+                decoded: false,
+                duplicate: false,
+                points: Vec::new(),
+                stats: None,
+                derived: None,
+            });
+        }
+
+        if submessage_counts.is_empty() {
+            return Err(Grib2Error::new(
+                Grib2ErrorCode::Empty,
+                "No GRIB2 messages in the data",
+            ));
+        }
+        for msg in &mut messages {
+            msg.submessage_count = submessage_counts[msg.message_index as usize];
+        }
+
+        // A file whose fields all use grids we can't map is an error, not an empty result
+        if let (true, Some(first)) = (messages.is_empty(), skipped.first()) {
+            let reason = format!("message {}: {}", first.message_index, first.error);
+            return Err(Grib2Error::new(Grib2ErrorCode::UnsupportedTemplate, reason));
+        }
+
+        Ok(Grib2Reader {
+            id: NEXT_READER_ID.fetch_add(1, Ordering::Relaxed),
+            files: vec![grib2],
+            sources: vec![source],
+            content: vec![None], // set by the constructors that know the file
+            messages,
+            filter: *filter,
+            current_message: 0,
+            current_point: 0,
+            stream: None,
+            skipped,
+            wind: None,
+            point_budget: usize::MAX,
+            emitted: 0,
+        })
+    }
+
+    /// Open a file, plain or gzip/bzip2-compressed with those features; messages are
+    /// indexed from their headers now and their values decoded as points are read
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Grib2Error> {
+        Self::new_filtered(path, &MessageFilter::default())
+    }
+
+    /// Open from file path, decoding only messages accepted by `filter`
+    fn new_filtered(path: impl AsRef<Path>, filter: &MessageFilter) -> Result<Self, Grib2Error> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| Grib2Error::io("Failed to open file", e))?;
+        let mut reader = Self::from_reader(BufReader::new(file), filter)?;
+        reader.content = vec![cache::ContentKey::of_file(path)];
+        Ok(reader)
+    }
+
+    /// Open only the first field matching `filter`'s parameter, found by walking the
+    /// message and section headers; messages after the one holding it are never read
+    /// Its message index and extent are those in the whole file. A matching field that
+    /// is dropped (e.g. for its grid) is passed over; if no other field matches, the
+    /// reason it was dropped is the error rather than NotFound
+    fn first_match(path: impl AsRef<Path>, filter: &MessageFilter) -> Result<Self, Grib2Error> {
+        let io = |e| Grib2Error::io("Failed to read file", e);
+        let file = File::open(path).map_err(|e| Grib2Error::io("Failed to open file", e))?;
+        let mut source = decompressed(BufReader::new(file))?;
+        let mut from = (0, 0);
+        let mut dropped = None;
+        loop {
+            let Some((message_index, offset, length)) = next_match(&mut source, from, filter)?
+            else {
+                return Err(dropped.unwrap_or_else(|| {
+                    Grib2Error::new(
+                        Grib2ErrorCode::NotFound,
+                        "No message matches the parameter filter",
+                    )
+                }));
+            };
+            from = (message_index + 1, offset + length);
+            let mut bytes = vec![0; length as usize];
+            source.seek(SeekFrom::Start(offset)).map_err(io)?;
+            source.read_exact(&mut bytes).map_err(io)?;
+            // The headers matched, but the field may still be dropped, e.g. for its grid;
+            // any other failure is the matching message's and is reported as is
+            let mut reader = match Self::from_reader(Cursor::new(bytes), filter) {
+                Ok(reader) => reader,
+                Err(e) if e.code == Grib2ErrorCode::UnsupportedTemplate => {
+                    dropped = Some(e);
+                    continue;
+                }
+                Err(e) => {
+                    let message = format!("message {message_index}: {}", e.message);
+                    return Err(Grib2Error::new(e.code, message));
+                }
+            };
+            if reader.messages.is_empty() {
+                continue;
+            }
+            reader.messages.truncate(1);
+            reader.skipped.clear();
+            let msg = &mut reader.messages[0];
+            msg.message_index = message_index;
+            msg.extent.0 += offset;
+            // `content` stays unset: submessage positions count from this message, so
+            // decoded values must not be cached under the file's identity
+            return Ok(reader);
+        }
+    }
+
+    /// Open several files as one message stream, in the order given
+    /// Messages keep their per-file indices and are tagged with the file's position.
+    /// A file that fails to open aborts, or with `skip_failed` is recorded in the
+    /// skip diagnostics instead; no file opening is an error either way
+    fn new_multi(paths: &[&str], skip_failed: bool) -> Result<Self, Grib2Error> {
+        let mut files = Vec::new();
+        let mut sources = Vec::new();
+        let mut content = Vec::new();
+        let mut messages = Vec::new();
+        let mut skipped = Vec::new();
+        let mut last_error = None;
+
+        for (source_index, path) in paths.iter().enumerate() {
+            let source_index = source_index as u32;
+            match Self::open(path) {
+                Ok(reader) => {
+                    let file = files.len();
+                    files.extend(reader.files);
+                    sources.extend(reader.sources);
+                    content.extend(reader.content);
+                    messages.extend(reader.messages.into_iter().map(|msg| ParsedMessage {
+                        file,
+                        source_index,
+                        ..msg
+                    }));
+                    skipped.extend(reader.skipped.into_iter().map(|entry| SkippedMessage {
+                        source_index,
+                        ..entry
+                    }));
+                }
+                Err(e) => {
+                    let e = Grib2Error::new(e.code, format!("{}: {}", path, e.message));
+                    if !skip_failed {
+                        return Err(e);
+                    }
+                    log_event!(Warn, "source {} skipped: {}", source_index, e.message);
+                    skipped.push(SkippedMessage {
+                        source_index,
+                        message_index: 0,
+                        submessage_index: 0,
+                        reason: Grib2SkipReason::OpenFailed,
+                        error: e.message.clone(),
+                    });
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        if files.is_empty() {
+            return Err(last_error.unwrap_or_else(|| {
+                Grib2Error::new(Grib2ErrorCode::InvalidArgument, "No paths given")
+            }));
+        }
+        Ok(Grib2Reader {
+            id: NEXT_READER_ID.fetch_add(1, Ordering::Relaxed),
+            files,
+            sources,
+            content,
+            messages,
+            filter: MessageFilter::default(),
+            current_message: 0,
+            current_point: 0,
+            stream: None,
+            skipped,
+            wind: None,
+            point_budget: usize::MAX,
+            emitted: 0,
+        })
+    }
+
+    /// Append `other`'s messages after this reader's, as grib2_open_multi does for files:
+    /// they keep their message numbers while their source_index moves past ours. The
+    /// result streams from the start with this reader's filter and point budget;
+    /// derived wind messages and dup policy marks are dropped, as they may now pair
+    /// across the two. Batches handed out by `other` count as ours from here on
+    pub fn merge(mut self, other: Grib2Reader) -> Grib2Reader {
+        let file_offset = self.files.len();
+        let source_offset = self
+            .messages
+            .iter()
+            .map(|m| m.source_index)
+            .chain(self.skipped.iter().map(|s| s.source_index))
+            .max()
+            .map_or(0, |last| last + 1)
+            .max(file_offset as u32);
+
+        self.messages.retain(|m| m.derived.is_none());
+        self.messages.extend(
+            other
+                .messages
+                .into_iter()
+                .filter(|m| m.derived.is_none())
+                .map(|msg| ParsedMessage {
+                    file: msg.file + file_offset,
+                    source_index: msg.source_index + source_offset,
+                    ..msg
+                }),
+        );
+        for msg in &mut self.messages {
+            msg.duplicate = false;
+        }
+        self.skipped
+            .extend(other.skipped.into_iter().map(|entry| SkippedMessage {
+                source_index: entry.source_index + source_offset,
+                ..entry
+            }));
+        self.files.extend(other.files);
+        self.sources.extend(other.sources);
+        self.content.extend(other.content);
+        for id in outstanding_batches().values_mut() {
+            if *id == other.id {
+                *id = self.id;
+            }
+        }
+
+        self.current_message = 0;
+        self.current_point = 0;
+        self.stream = None;
+        self.wind = None;
+        self.emitted = 0;
+        self
+    }
+
+    /// Open from a memory-mapped file; the mapping is owned by (and lives as long as) the reader
+    #[cfg(feature = "mmap")]
+    fn new_mmap(path: &str) -> Result<Self, Grib2Error> {
+        let file = File::open(path).map_err(|e| Grib2Error::io("Failed to open file", e))?;
+        // Safety: like any mmap reader this assumes the file isn't truncated while open
+        let map = unsafe { memmap2::Mmap::map(&file) }
+            .map_err(|e| Grib2Error::io("Failed to map file", e))?;
+        let mut reader = Self::from_reader(Cursor::new(map), &MessageFilter::default())?;
+        reader.content = vec![cache::ContentKey::of_file(Path::new(path))];
+        Ok(reader)
+    }
+
+    /// Open from in-memory bytes (copies data to owned Vec for Seek support)
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Grib2Error> {
+        let owned_data = data.to_vec();
+        let cursor = Cursor::new(owned_data);
+        Self::from_reader(cursor, &MessageFilter::default())
+    }
+
+    /// Open over bytes owned by the caller, without copying them
+    /// The `'static` bound stands in for "until grib2_close", which the caller guarantees
+    fn from_borrowed_bytes(data: &'static [u8]) -> Result<Self, Grib2Error> {
+        Self::from_reader(Cursor::new(data), &MessageFilter::default())
+    }
+
+    /// Open over host I/O callbacks, buffered so small reads don't each cross into C
+    fn from_callbacks(source: CallbackSource) -> Result<Self, Grib2Error> {
+        Self::from_reader(BufReader::new(source), &MessageFilter::default())
+    }
+
+    /// Fetch a file or byte range over HTTP(S) and index it like in-memory bytes
+    #[cfg(feature = "remote")]
+    fn from_url(url: &str, offset: u64, length: u64) -> Result<Self, Grib2Error> {
+        let body = fetch_range(url, offset, length)?;
+        Self::from_reader(Cursor::new(body), &MessageFilter::default())
+    }
+
+    /// Read the coordinates and section 5-7 payloads of one message
+    /// This is the only part of decoding that touches the shared file handle
+    fn load_submessage(&self, msg: &ParsedMessage) -> Result<(GridPoints, Unpacker), SkipCause> {
+        let (_, submessage) = self.files[msg.file].iter().nth(msg.submessage).ok_or((
+            Grib2SkipReason::DecodeFailed,
+            "Message is no longer readable".to_string(),
+        ))?;
+        let latlons = grid_points(&submessage).ok_or((
+            Grib2SkipReason::NoCoordinates,
+            "Grid coordinates could not be computed".to_string(),
+        ))?;
+        Ok((latlons, self.unpacker(msg, submessage)?))
+    }
+
+    /// Read the section 5-7 payloads of a submessage, raw for complex packing
+    fn unpacker(
+        &self,
+        msg: &ParsedMessage,
+        submessage: SubMessage<SeekableGrib2Reader<SharedSource>>,
+    ) -> Result<Unpacker, SkipCause> {
+        let repr = submessage.repr_def();
+        let read = |section: &SectionInfo| {
+            self.sources[msg.file].read_payload(section).map_err(|e| {
+                let error = format!("Failed to read section {}: {e}", section.num);
+                (Grib2SkipReason::DecodeFailed, error)
+            })
+        };
+        let bitmap = || match &submessage.6.body.body {
+            Some(SectionBody::Section6(b)) if b.bitmap_indicator == 255 => Ok(None),
+            Some(SectionBody::Section6(b)) if b.bitmap_indicator == 0 => {
+                Ok(Some(read(submessage.6.body)?.split_off(1)))
+            }
+            _ => Err((
+                Grib2SkipReason::UnsupportedPacking,
+                "Only bitmaps given in section 6 are supported".to_string(),
+            )),
+        };
+        // Filled here rather than by the crate, whose constant fields skip the 10^-D
+        // scaling; this also leaves section 7 unread and needs no codec
+        if let (true, Some((reference, _, decimal))) = (constant_field(msg), msg.packing_scale) {
+            return Ok(Unpacker::Constant {
+                value: (f64::from(reference) / 10f64.powi(decimal.into())) as f32,
+                bitmap: bitmap()?,
+                num_points: submessage.grid_def().num_points() as usize,
+            });
+        }
+        if let Some(error) = disabled_codec(repr.repr_tmpl_num()) {
+            return Err((Grib2SkipReason::UnsupportedPacking, error));
+        }
+        if !matches!(repr.repr_tmpl_num(), 2 | 3) {
+            let decoder = Grib2SubmessageDecoder::from(submessage).map_err(unpack_error)?;
+            return Ok(Unpacker::Decoder(decoder));
+        }
+        Ok(Unpacker::Complex(complex::ComplexPacked {
+            repr: repr.iter().as_slice().to_vec(),
+            bitmap: bitmap()?,
+            data: read(submessage.7.body)?,
+            num_points: submessage.grid_def().num_points() as usize,
+        }))
+    }
+
+    /// Coordinates of a submessage in scan order
+    /// The submessage borrowing the file handle is released before returning
+    fn submessage_points(&self, msg: &ParsedMessage) -> Option<GridPoints> {
+        let (_, submessage) = self.files[msg.file].iter().nth(msg.submessage)?;
+        grid_points(&submessage)
+    }
+
+    /// Decode the values of one message, applying the bbox crop and missing-value policy
+    /// Returns None when the grid or packing can't be decoded
+    fn decode_points(&self, index: usize) -> Option<Vec<(f64, f64, f64)>> {
+        let msg = self.messages.get(index)?;
+        // Derived and cached values come unpacked whole
+        if msg.derived.is_some() || self.cache_key(msg).is_some() {
+            let latlons = self.submessage_points(msg)?;
+            let values = self.decode_values(index)?;
+            let filter = self.filter.for_message(msg);
+            return Some(filter.collect_points(latlons, values.iter().copied()));
+        }
+        let (latlons, decoder) = self.load_submessage(msg).ok()?;
+        let values = decoder.values().ok()?;
+        Some(self.filter.for_message(msg).collect_points(latlons, values))
+    }
+
+    /// Decode-cache key of a message read from a file, None when caching is off
+    fn cache_key(&self, msg: &ParsedMessage) -> Option<cache::MessageKey> {
+        if msg.derived.is_some() || !cache::enabled() {
+            return None;
+        }
+        let content = self.content.get(msg.file)?.clone()?;
+        Some((content, msg.submessage))
+    }
+
+    /// All values of a file message in scan order, from the decode cache when it
+    /// holds them; freshly unpacked values are added to it
+    fn unpacked_values(&self, msg: &ParsedMessage) -> Result<Arc<[f32]>, SkipCause> {
+        let key = self.cache_key(msg);
+        if let Some(values) = key.as_ref().and_then(cache::get) {
+            return Ok(values);
+        }
+        let (_, submessage) = self.files[msg.file].iter().nth(msg.submessage).ok_or((
+            Grib2SkipReason::DecodeFailed,
+            "Message is no longer readable".to_string(),
+        ))?;
+        let values: Arc<[f32]> = self.unpacker(msg, submessage)?.values()?.collect();
+        if let Some(key) = key {
+            cache::insert(key, values.clone());
+        }
+        Ok(values)
+    }
+
+    /// Open a lazy stream over the points of one message
+    /// Fails when the grid or packing can't be decoded
+    fn stream_points(&self, index: usize) -> Result<PointStream, SkipCause> {
+        let msg = &self.messages[index];
+        if msg.derived.is_some() {
+            let latlons = self.submessage_points(msg).ok_or((
+                Grib2SkipReason::NoCoordinates,
+                "Grid coordinates could not be computed".to_string(),
+            ))?;
+            let values = self.decode_values(index).ok_or((
+                Grib2SkipReason::DecodeFailed,
+                "Failed to decode the wind components".to_string(),
+            ))?;
+            let filter = self.filter.for_message(msg);
+            return Ok(PointStream::from_values(latlons, values, filter));
+        }
+        if self.cache_key(msg).is_some() {
+            let latlons = self.submessage_points(msg).ok_or((
+                Grib2SkipReason::NoCoordinates,
+                "Grid coordinates could not be computed".to_string(),
+            ))?;
+            let values = self.unpacked_values(msg)?;
+            let filter = self.filter.for_message(msg);
+            return Ok(PointStream::from_values(latlons, values, filter));
+        }
+        let (latlons, decoder) = self.load_submessage(msg)?;
+        PointStream::decode(latlons, decoder, self.filter.for_message(msg))
+    }
+
+    /// Fail on the first message that was dropped at open or whose values can't be
+    /// unpacked; reads the data sections of every message once
+    fn check_strict(&self) -> Result<(), Grib2Error> {
+        let error = |message_index: u32, (reason, error): SkipCause| {
+            let code = match reason {
+                Grib2SkipReason::DecodeFailed => Grib2ErrorCode::DecodeFailed,
+                _ => Grib2ErrorCode::UnsupportedTemplate,
+            };
+            Grib2Error::new(code, format!("message {}: {}", message_index, error))
+        };
+        if let Some(skipped) = self.skipped.first() {
+            let cause = (skipped.reason, skipped.error.clone());
+            return Err(error(skipped.message_index, cause));
+        }
+        for msg in &self.messages {
+            let (_, decoder) = self
+                .load_submessage(msg)
+                .map_err(|cause| error(msg.message_index, cause))?;
+            let unpacked = decoder.values().map(|_| ());
+            unpacked.map_err(|cause| error(msg.message_index, cause))?;
+        }
+        Ok(())
+    }
+
+    /// Remember why a message produced no points; repeated scans don't add duplicates
+    fn record_skip(&mut self, index: usize, (reason, error): SkipCause) {
+        let msg = &self.messages[index];
+        let skipped = SkippedMessage {
+            source_index: msg.source_index,
+            message_index: msg.message_index,
+            submessage_index: msg.submessage_index,
+            reason,
+            error,
+        };
+        if !self.skipped.contains(&skipped) {
+            log_event!(
+                Warn,
+                "message {}.{} produced no points: {}",
+                skipped.message_index,
+                skipped.submessage_index,
+                skipped.error
+            );
+            self.skipped.push(skipped);
+        }
+    }
+
+    /// Decode every message up front, unpacking values on the rayon thread pool
+    /// Section payloads are still read sequentially; message order is preserved
+    #[cfg(feature = "parallel")]
+    fn decode_all_parallel(&mut self) {
+        use rayon::prelude::*;
+
+        let loaded: Vec<_> = self
+            .messages
+            .iter()
+            .map(|m| (self.load_submessage(m), self.filter.for_message(m)))
+            .collect();
+        let decoded: Vec<_> = loaded
+            .into_par_iter()
+            .map(|(job, filter)| {
+                let (latlons, decoder) = job?;
+                let values = decoder.values()?;
+                Ok(filter.collect_points(latlons, values))
+            })
+            .collect();
+
+        for (index, points) in decoded.into_iter().enumerate() {
+            let points = points.unwrap_or_else(|cause| {
+                self.record_skip(index, cause);
+                Vec::new()
+            });
+            let msg = &mut self.messages[index];
+            msg.stats = Some(ValueStats::from_points(&points));
+            msg.points = points;
+            msg.decoded = true;
+        }
+    }
+
+    /// Decode all values of one message in native scan order, ignoring any bbox crop
+    fn decode_values(&self, index: usize) -> Option<Arc<[f32]>> {
+        let msg = self.messages.get(index)?;
+        if let Some(derived) = msg.derived {
+            let (u, v) = derived.sources();
+            let (u, v) = (self.decode_values(u)?, self.decode_values(v)?);
+            if u.len() != v.len() {
+                return None;
+            }
+            return Some(
+                u.iter()
+                    .zip(v.iter())
+                    .map(|(&u, &v)| derived.combine(u, v))
+                    .collect(),
+            );
+        }
+        self.unpacked_values(msg).ok()
+    }
+
+    /// Value of the grid point nearest to (lat, lon)
+    fn point_query(&self, index: usize, lat: f64, lon: f64) -> Result<f64, Grib2QueryStatus> {
+        let msg = self
+            .messages
+            .get(index)
+            .ok_or(Grib2QueryStatus::InvalidMessage)?;
+
+        if let Some(grid) = &msg.latlon_grid {
+            let (fi, fj) = grid
+                .fractional_index(lat, lon)
+                .ok_or(Grib2QueryStatus::OutsideGrid)?;
+            let values = self
+                .decode_values(index)
+                .ok_or(Grib2QueryStatus::DecodeFailed)?;
+            let flat = grid.flat_index(fi.round() as usize, fj.round() as usize);
+            return values
+                .get(flat)
+                .map(|v| *v as f64)
+                .ok_or(Grib2QueryStatus::DecodeFailed);
+        }
+
+        // Other grids: scan every point for the smallest great-circle distance
+        let latlons = self
+            .submessage_points(msg)
+            .ok_or(Grib2QueryStatus::UnsupportedGrid)?;
+        let values = self
+            .decode_values(index)
+            .ok_or(Grib2QueryStatus::DecodeFailed)?;
+        latlons
+            .zip(values.iter().copied())
+            .map(|((plat, plon), value)| (haversine_km(lat, lon, plat as f64, plon as f64), value))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, value)| value as f64)
+            .ok_or(Grib2QueryStatus::DecodeFailed)
+    }
+
+    /// Value of grid cell (i, j), counted from the first grid point along the scanning
+    /// directions; decodes the message's values on each call
+    fn value_at(&self, index: usize, i: usize, j: usize) -> Result<f64, Grib2QueryStatus> {
+        let msg = self
+            .messages
+            .get(index)
+            .ok_or(Grib2QueryStatus::InvalidMessage)?;
+        // Reduced and other irregular grids have no (i, j) layout
+        let grid = msg.grid;
+        if !grid.is_known() || grid.ni as usize * grid.nj as usize != msg.num_points {
+            return Err(Grib2QueryStatus::UnsupportedGrid);
+        }
+        let flat = grid.flat_index(i, j).ok_or(Grib2QueryStatus::OutsideGrid)?;
+        let values = self
+            .decode_values(index)
+            .ok_or(Grib2QueryStatus::DecodeFailed)?;
+        values
+            .get(flat)
+            .map(|v| *v as f64)
+            .ok_or(Grib2QueryStatus::DecodeFailed)
+    }
+
+    /// Bilinear interpolation from the four surrounding points of a regular lat/lon grid
+    fn interpolate(
+        &self,
+        index: usize,
+        lat: f64,
+        lon: f64,
+    ) -> Result<(f64, Grib2QueryStatus), Grib2QueryStatus> {
+        let msg = self
+            .messages
+            .get(index)
+            .ok_or(Grib2QueryStatus::InvalidMessage)?;
+        let grid = msg
+            .latlon_grid
+            .as_ref()
+            .ok_or(Grib2QueryStatus::UnsupportedGrid)?;
+        let (fi, fj) = grid
+            .fractional_index(lat, lon)
+            .ok_or(Grib2QueryStatus::OutsideGrid)?;
+        let values = self
+            .decode_values(index)
+            .ok_or(Grib2QueryStatus::DecodeFailed)?;
+        let value_at = |i: usize, j: usize| {
+            values
+                .get(grid.flat_index(i, j))
+                .map(|v| *v as f64)
+                .unwrap_or(f64::NAN)
+        };
+
+        let (i0, j0) = (fi.floor() as usize, fj.floor() as usize);
+        let i1 = if grid.is_global() { i0 + 1 } else { (i0 + 1).min(grid.ni - 1) };
+        let j1 = (j0 + 1).min(grid.nj - 1);
+        let (wx, wy) = (fi - i0 as f64, fj - j0 as f64);
+
+        let corners = [value_at(i0, j0), value_at(i1, j0), value_at(i0, j1), value_at(i1, j1)];
+        if corners.iter().any(|v| v.is_nan()) {
+            let nearest = value_at(fi.round() as usize, fj.round() as usize);
+            return Ok((nearest, Grib2QueryStatus::NearestFallback));
+        }
+
+        let top = corners[0] * (1.0 - wx) + corners[1] * wx;
+        let bottom = corners[2] * (1.0 - wx) + corners[3] * wx;
+        Ok((top * (1.0 - wy) + bottom * wy, Grib2QueryStatus::Ok))
+    }
+
+    /// Resample one message onto a regular lat/lon mesh, returning the batch and its
+    /// (columns, rows); points keep the message metadata, rows run north to south
+    fn regrid_message(
+        &self,
+        index: usize,
+        lat_step: f64,
+        lon_step: f64,
+        method: Grib2RegridMethod,
+    ) -> Result<(Grib2Batch, usize, usize), String> {
+        let msg = self
+            .messages
+            .get(index)
+            .ok_or_else(|| format!("No message at index {index}"))?;
+        let points = if msg.decoded {
+            None
+        } else {
+            Some(
+                self.decode_points(index)
+                    .ok_or_else(|| format!("Failed to decode message {index}"))?,
+            )
+        };
+        let points = points.as_deref().unwrap_or(&msg.points);
+        let mesh = regrid::regrid(points, lat_step, lon_step, method).ok_or_else(|| {
+            "Steps must be positive, the message must have points and the mesh must have \
+                 at most 2^26 nodes"
+                .to_string()
+        })?;
+
+        let points = mesh
+            .points
+            .into_iter()
+            .map(|point| Grib2DataPoint::new(msg, point))
+            .collect();
+        let (data, count) = leak_batch(self.id, points);
+        let batch = Grib2Batch {
+            data,
+            count,
+            has_more: false,
+            error: ptr::null_mut(),
+        };
+        Ok((batch, mesh.ni, mesh.nj))
+    }
+
+    /// Build one message as an Arrow record batch exported through the C Data
+    /// Interface; points follow the reader's filter like read_batch
+    #[cfg(feature = "arrow")]
+    fn export_arrow(
+        &self,
+        index: usize,
+    ) -> Result<
+        (
+            arrow_data::ffi::FFI_ArrowArray,
+            arrow_schema::ffi::FFI_ArrowSchema,
+        ),
+        String,
+    > {
+        let msg = self
+            .messages
+            .get(index)
+            .ok_or_else(|| format!("No message at index {index}"))?;
+        let points = if msg.decoded {
+            None
+        } else {
+            Some(
+                self.decode_points(index)
+                    .ok_or_else(|| format!("Failed to decode message {index}"))?,
+            )
+        };
+        let points = points.as_deref().unwrap_or(&msg.points);
+        let batch = arrow::record_batch(msg, points).map_err(|e| e.to_string())?;
+        arrow::export(batch).map_err(|e| e.to_string())
+    }
+
+    /// Write one message's whole grid as a north-up GeoTIFF, ignoring the bbox and
+    /// missing-value policy; only regular lat/lon grids (template 3.0) have a geotransform
+    /// The file is created once the message has decoded, so failures leave none behind
+    #[cfg(feature = "geotiff")]
+    fn write_geotiff(&self, index: usize, path: &Path) -> Result<(), Grib2Error> {
+        let msg = self.messages.get(index).ok_or_else(|| {
+            Grib2Error::new(
+                Grib2ErrorCode::InvalidArgument,
+                format!("No message at index {index}"),
+            )
+        })?;
+        let grid = msg.latlon_grid.ok_or_else(|| {
+            Grib2Error::new(
+                Grib2ErrorCode::UnsupportedTemplate,
+                format!("Message {index} is not on a regular lat/lon grid (template 3.0)"),
+            )
+        })?;
+        let values = self
+            .decode_values(index)
+            .filter(|values| values.len() == grid.ni * grid.nj)
+            .ok_or_else(|| {
+                Grib2Error::new(
+                    Grib2ErrorCode::DecodeFailed,
+                    format!("Failed to decode message {index}"),
+                )
+            })?;
+
+        // Rows north to south and columns west to east, whatever the scanning mode
+        let (ni, nj) = (grid.ni, grid.nj);
+        let mut raster = vec![f32::NAN; ni * nj];
+        for (k, &value) in values.iter().enumerate() {
+            let (i, j) = grid.cell(k);
+            let row = if grid.lat_step > 0.0 { nj - 1 - j } else { j };
+            let column = if grid.lon_step < 0.0 { ni - 1 - i } else { i };
+            raster[row * ni + column] = value;
+        }
+        let west = grid.first_lon + grid.lon_step.min(0.0) * (ni - 1) as f64;
+        let raster = geotiff::Raster {
+            width: ni,
+            height: nj,
+            values: &raster,
+            west: normalize_longitude(west, Grib2LonConvention::Signed),
+            north: grid.first_lat + grid.lat_step.max(0.0) * (nj - 1) as f64,
+            dx: grid.lon_step.abs(),
+            dy: grid.lat_step.abs(),
+        };
+        let file = File::create(path).map_err(|e| Grib2Error::io("Failed to create file", e))?;
+        geotiff::write(BufWriter::new(file), &raster)
+            .map_err(|e| Grib2Error::io("Failed to write GeoTIFF", e))
+    }
+
+    /// Append wind speed and direction messages for every U/V pair sharing a
+    /// level, time and grid; U messages without a matching V are skipped
+    /// Repeated calls return the messages appended by the first one
+    fn derive_wind(&mut self) -> WindMessages {
+        if let Some(wind) = self.wind {
+            return wind;
+        }
+
+        let same_field = |a: &ParsedMessage, b: &ParsedMessage| {
+            a.reference_time == b.reference_time
+                && a.forecast_time == b.forecast_time
+                && a.surface_type == b.surface_type
+                && a.surface_value == b.surface_value
+                && a.surface_type_2 == b.surface_type_2
+                && a.surface_value_2 == b.surface_value_2
+                && a.ensemble_member == b.ensemble_member
+                && a.statistical_process == b.statistical_process
+                && a.time_range_seconds == b.time_range_seconds
+                && a.num_points == b.num_points
+                && (a.grid.ni, a.grid.nj, a.grid.scan_mode)
+                    == (b.grid.ni, b.grid.nj, b.grid.scan_mode)
+        };
+        let is_wind = |m: &ParsedMessage, number: u8| {
+            m.derived.is_none()
+                && (m.discipline, m.parameter_category, m.parameter_number) == (0, 2, number)
+        };
+
+        let mut pairs = Vec::new();
+        let mut used = vec![false; self.messages.len()];
+        for (u, u_msg) in self.messages.iter().enumerate() {
+            if !is_wind(u_msg, 2) {
+                continue;
+            }
+            let v =
+                self.messages.iter().enumerate().position(|(v, v_msg)| {
+                    !used[v] && is_wind(v_msg, 3) && same_field(u_msg, v_msg)
+                });
+            if let Some(v) = v {
+                used[v] = true;
+                pairs.push((u, v));
+            }
+        }
+
+        let synthetic = |u: usize, parameter_number: u8, derived: Derived| ParsedMessage {
+            parameter_number,
+            decoded: false,
+            duplicate: false,
+            points: Vec::new(),
+            stats: None,
+            derived: Some(derived),
+            ..self.messages[u].clone()
+        };
+        let speed: Vec<_> = pairs
+            .iter()
+            .map(|&(u, v)| synthetic(u, 1, Derived::WindSpeed { u, v }))
+            .collect();
+        let direction: Vec<_> = pairs
+            .iter()
+            .map(|&(u, v)| synthetic(u, 0, Derived::WindDirection { u, v }))
+            .collect();
+
+        let wind = WindMessages {
+            speed: self.messages.len(),
+            direction: self.messages.len() + pairs.len(),
+            count: pairs.len(),
+        };
+        self.messages.extend(speed);
+        self.messages.extend(direction);
+        self.wind = Some(wind);
+        wind
+    }
+
+    /// Value statistics of a message, decoding it once if it hasn't been streamed yet
+    fn message_stats(&mut self, index: usize) -> Option<ValueStats> {
+        let msg = self.messages.get(index)?;
+        if let Some(stats) = msg.stats {
+            return Some(stats);
+        }
+        let stats = if msg.decoded {
+            ValueStats::from_points(&msg.points)
+        } else {
+            ValueStats::from_points(&self.decode_points(index)?)
+        };
+        self.messages[index].stats = Some(stats);
+        Some(stats)
+    }
+
+    /// Drop decoded points so memory stays bounded to about one message
+    fn release_decoded(&mut self, index: usize) {
+        let msg = &mut self.messages[index];
+        msg.points = Vec::new();
+        msg.decoded = false;
+    }
+
+    /// Advance the cursor point by point, handing each to `visit` until it returns false
+    /// or max_count points went by; returns how many points were visited
+    /// Messages not decoded up front are unpacked as they are pulled, so memory
+    /// scales with what the caller keeps rather than with the message size
+    fn visit_points(
+        &mut self,
+        max_count: usize,
+        mut visit: impl FnMut(&ParsedMessage, (f64, f64, f64)) -> bool,
+    ) -> usize {
+        let max_count = max_count.min(self.point_budget.saturating_sub(self.emitted));
+        let mut visited = 0;
+        let mut stopped = false;
+        while !stopped && visited < max_count && self.current_message < self.messages.len() {
+            let (count, stop) = self.visit_message(max_count - visited, visited > 0, &mut visit);
+            visited += count;
+            stopped = stop;
+        }
+        self.emitted += visited;
+        visited
+    }
+
+    /// Metadata of each indexed message, in file order
+    pub fn messages(&self) -> impl ExactSizeIterator<Item = Grib2MessageInfo> + '_ {
+        self.messages.iter().map(Grib2MessageInfo::new)
+    }
+
+    /// Iterate the points from the cursor on, decoding messages as they are reached
+    pub fn points(&mut self) -> Points<'_> {
+        Points { reader: self }
+    }
+
+    /// True when every message has the same section 3, byte for byte
+    fn has_uniform_grid(&self) -> bool {
+        self.messages
+            .windows(2)
+            .all(|pair| pair[0].grid_hash == pair[1].grid_hash)
+    }
+
+    /// True once the point budget stopped the reader with points still to come
+    fn budget_exceeded(&self) -> bool {
+        self.emitted >= self.point_budget && self.current_message < self.messages.len()
+    }
+
+    /// visit_points within the message under the cursor, moving past it once exhausted
+    /// Returns the points visited and whether `visit` asked to stop, or, when rows are
+    /// tracked, whether the next grid row doesn't fit in max_count. Only a row that
+    /// starts the batch (`started` false) is split to fit
+    fn visit_message(
+        &mut self,
+        max_count: usize,
+        started: bool,
+        mut visit: impl FnMut(&ParsedMessage, (f64, f64, f64)) -> bool,
+    ) -> (usize, bool) {
+        let mut visited = 0;
+        let mut stopped = false;
+        let mut row_end = 0; // `visited` at the end of the row under the cursor
+        let index = self.current_message;
+        // How much of a row with `left` points still to come fits; 0 ends the batch
+        let row_take = |left: usize, visited: usize| {
+            let budget = max_count - visited;
+            if left <= budget {
+                left
+            } else if started || visited > 0 {
+                0
+            } else {
+                budget
+            }
+        };
+
+        let exhausted = if self.messages[index].duplicate {
+            // Left out by the dup policy; a stream opened before it was set goes too
+            self.stream = None;
+            true
+        } else if self.messages[index].decoded {
+            // Already in memory, e.g. after grib2_open_parallel
+            let msg = &self.messages[index];
+            while !stopped && visited < max_count && self.current_point < msg.points.len() {
+                stopped = !visit(msg, msg.points[self.current_point]);
+                self.current_point += 1;
+                visited += 1;
+            }
+            self.current_point >= msg.points.len()
+        } else {
+            // A message that fails to decode streams no points
+            let mut stream = match self.stream.take() {
+                Some(stream) => stream,
+                None => self.stream_points(index).unwrap_or_else(|cause| {
+                    self.record_skip(index, cause);
+                    PointStream::empty()
+                }),
+            };
+            let msg = &self.messages[index];
+            while !stopped && visited < max_count {
+                if stream.rows && visited == row_end {
+                    let left = stream.next_row();
+                    let take = row_take(left, visited);
+                    if take == 0 {
+                        stopped = left > 0;
+                        break;
+                    }
+                    row_end = visited + take;
+                }
+                let Some(point) = stream.next() else {
+                    break;
+                };
+                stopped = !visit(msg, point);
+                self.current_point += 1;
+                visited += 1;
+            }
+            // Peek so has_more turns false as soon as the last point is out
+            let exhausted = stream.is_exhausted();
+            if !exhausted {
+                self.stream = Some(stream);
+            }
+            exhausted
+        };
+
+        if exhausted {
+            self.release_decoded(index);
+            self.current_message += 1;
+            self.current_point = 0;
+        }
+        (visited, stopped)
+    }
+
+    /// Advance the cursor by up to n points without building any; returns how many
+    /// went by. Whole messages whose points all pass the filter are stepped over by
+    /// their section 3 point count without unpacking them, so a message whose packing
+    /// fails to decode counts as that many points rather than none
+    fn skip_points(&mut self, n: usize) -> usize {
+        let mut skipped = 0;
+        while skipped < n && self.current_message < self.messages.len() {
+            let index = self.current_message;
+            let msg = &self.messages[index];
+            let untouched = self.current_point == 0 && self.stream.is_none() && !msg.decoded;
+            let filter = self.filter.for_message(msg);
+            let keeps_all = !filter.skip_missing && filter.bbox.is_none() && filter.seam.is_none();
+            let whole = msg.derived.is_none() && !msg.duplicate && msg.num_points <= n - skipped;
+            if untouched && keeps_all && whole {
+                skipped += msg.num_points;
+                self.move_cursor(index + 1);
+                continue;
+            }
+            skipped += self.visit_message(n - skipped, false, |_, _| true).0;
+        }
+        skipped
+    }
+
+    /// Rewind and write every point as a CSV row with a header; values are streamed
+    /// through a buffered writer, so memory stays bounded by one message
+    /// Missing values are empty fields, as is the reference time when unknown
+    /// Fails, after writing the rows within it, when the point budget cuts the output short
+    fn write_csv(&mut self, out: impl Write) -> std::io::Result<usize> {
+        let mut out = BufWriter::with_capacity(1 << 20, out);
+        writeln!(
+            out,
+            "latitude,longitude,value,discipline,category,number,reference_time,\
+             forecast_time,surface_type,surface_value,message_index"
+        )?;
+        self.reset();
+        let mut result = Ok(());
+        // Formatted once per reference time rather than per row
+        let mut reference = (0, String::new());
+        let rows = self.visit_points(usize::MAX, |msg, (lat, lon, value)| {
+            if msg.reference_time != 0 && reference.0 != msg.reference_time {
+                reference = (msg.reference_time, iso_time(msg.reference_time));
+            }
+            let reference_time = if msg.reference_time != 0 {
+                reference.1.as_str()
+            } else {
+                ""
+            };
+            let value = if value.is_nan() {
+                String::new()
+            } else {
+                value.to_string()
+            };
+            result = writeln!(
+                out,
+                "{lat},{lon},{value},{},{},{},{reference_time},{},{},{},{}",
+                msg.discipline,
+                msg.parameter_category,
+                msg.parameter_number,
+                msg.forecast_time,
+                msg.surface_type,
+                msg.surface_value,
+                msg.message_index,
+            );
+            result.is_ok()
+        });
+        result?;
+        out.flush()?;
+        if self.budget_exceeded() {
+            return Err(std::io::Error::other(format!(
+                "point budget reached after {rows} points, the file is partial"
+            )));
+        }
+        Ok(rows)
+    }
+
+    /// Advance the cursor by up to max_count points, converting each with `emit`
+    fn next_points<T>(
+        &mut self,
+        max_count: usize,
+        mut emit: impl FnMut(&ParsedMessage, (f64, f64, f64)) -> T,
+    ) -> Vec<T> {
+        let mut points = Vec::with_capacity(max_count.min(self.total_points()));
+        self.visit_points(max_count, |msg, point| {
+            points.push(emit(msg, point));
+            true
+        });
+        points
+    }
+
+    /// Position of a field by its file, GRIB message and submessage numbers
+    /// Derived wind fields reuse the numbers of their U component, so are left out
+    fn find_submessage(&self, source: u32, message: u32, submessage: u32) -> Option<usize> {
+        self.messages.iter().position(|m| {
+            m.derived.is_none()
+                && (m.source_index, m.message_index, m.submessage_index)
+                    == (source, message, submessage)
+        })
+    }
+
+    /// Rewind the cursor so the indexed messages stream again without re-parsing
+    pub fn reset(&mut self) {
+        self.move_cursor(0);
+        self.emitted = 0;
+    }
+
+    /// Position the cursor at the first point of a message; false if out of range
+    fn seek_message(&mut self, index: usize) -> bool {
+        if index >= self.messages.len() {
+            return false;
+        }
+        self.move_cursor(index);
+        true
+    }
+
+    /// Position the cursor at a point of a message, as reported by tell: `point`
+    /// points into it after filtering. A point past the end of the message leaves
+    /// the cursor where it was and returns false; so does a message out of range,
+    /// except the end position (messages.len(), 0)
+    fn seek(&mut self, message: usize, point: usize) -> bool {
+        let len = self.messages.len();
+        if message > len || (message == len && point > 0) {
+            return false;
+        }
+        let saved = (self.current_message, self.current_point);
+        if self.advance_to(message, point) {
+            return true;
+        }
+        // Restoring a position tell reported can't fail
+        self.advance_to(saved.0, saved.1);
+        false
+    }
+
+    /// Move to the start of a message and pull `point` of its points; false when it
+    /// has fewer. Reaching exactly its last point lands on the next message
+    fn advance_to(&mut self, message: usize, point: usize) -> bool {
+        if self.current_message == message && self.messages.get(message).is_some_and(|m| m.decoded)
+        {
+            // Still in memory (e.g. after grib2_open_parallel), only the position moves
+            let in_range = point <= self.messages[message].points.len();
+            if in_range {
+                self.current_point = point;
+            }
+            return in_range;
+        }
+        self.move_cursor(message);
+        let mut advanced = 0;
+        while advanced < point && self.current_message == message {
+            advanced += self.visit_message(point - advanced, false, |_, _| true).0;
+        }
+        advanced == point
+    }
+
+    fn move_cursor(&mut self, index: usize) {
+        if self.current_message < self.messages.len() {
+            self.release_decoded(self.current_message);
+        }
+        self.stream = None;
+        self.current_message = index;
+        self.current_point = 0;
+    }
+
+    fn has_more(&self) -> bool {
+        self.current_message < self.messages.len() && self.emitted < self.point_budget
+    }
+
+    fn read_batch(&mut self, max_count: usize) -> Grib2Batch {
+        self.read_batch_converted(max_count, 0)
+    }
+
+    /// read_batch without moving the cursor
+    /// A message that was being streamed is re-streamed up to the old position
+    fn peek_batch(&mut self, max_count: usize) -> Grib2Batch {
+        let (message, point, emitted) = (self.current_message, self.current_point, self.emitted);
+        let batch = self.read_batch(max_count);
+        self.emitted = emitted;
+        if (self.current_message, self.current_point) != (message, point) {
+            self.advance_to(message, point);
+        }
+        batch
+    }
+
+    /// read_batch with values converted per parameter according to unit_mode
+    fn read_batch_converted(&mut self, max_count: usize, unit_mode: u32) -> Grib2Batch {
+        // Points arrive grouped by message, so look up once per message run
+        let mut current: Option<(*const ParsedMessage, _)> = None;
+        let points = self.next_points(max_count, |msg, point| {
+            let mut point = Grib2DataPoint::new(msg, point);
+            let conversion = match current {
+                Some((m, conversion)) if ptr::eq(m, msg) => conversion,
+                _ => {
+                    let conversion = msg
+                        .parameter()
+                        .and_then(|p| parameters::conversion(p, unit_mode));
+                    current = Some((msg, conversion));
+                    conversion
+                }
+            };
+            if let Some(conversion) = conversion {
+                point.value = conversion.apply(point.value);
+            }
+            point
+        });
+        let (data, count) = leak_batch(self.id, points);
+        Grib2Batch {
+            data,
+            count,
+            has_more: count > 0 && self.has_more(),
+            error: ptr::null_mut(),
+        }
+    }
+
+    fn read_batch_f32(&mut self, max_count: usize) -> Grib2BatchF32 {
+        let points = self.next_points(max_count, Grib2DataPointF32::new);
+        let (data, count) = leak_batch(self.id, points);
+        Grib2BatchF32 {
+            data,
+            count,
+            has_more: count > 0 && self.has_more(),
+            error: ptr::null_mut(),
+        }
+    }
+
+    fn read_batch_raw(&mut self, max_count: usize) -> Grib2BatchRaw {
+        let points = self.next_points(max_count, |msg, point| {
+            // Derived wind is computed, not unpacked, whatever the scale it inherited;
+            // 5.61 takes a logarithm before packing, so X isn't linear in the value
+            let linear = msg.derived.is_none() && msg.packing_template != 61;
+            let scale = msg.packing_scale.filter(|_| linear);
+            Grib2DataPointRaw {
+                point: Grib2DataPoint::new(msg, point),
+                raw: packed_integer(scale, point.2),
+                categorical: msg.parameter().is_some_and(parameters::is_categorical),
+            }
+        });
+        let (data, count) = leak_batch(self.id, points);
+        Grib2BatchRaw {
+            data,
+            count,
+            has_more: count > 0 && self.has_more(),
+            error: ptr::null_mut(),
+        }
+    }
+
+    #[cfg(feature = "h3")]
+    fn read_batch_h3(&mut self, max_count: usize, resolution: h3o::Resolution) -> Grib2BatchH3 {
+        let points = self.next_points(max_count, |msg, point| Grib2DataPointH3 {
+            point: Grib2DataPoint::new(msg, point),
+            h3_cell: h3_cell(point.0, point.1, resolution),
+        });
+        let (data, count) = leak_batch(self.id, points);
+        Grib2BatchH3 {
+            data,
+            count,
+            has_more: count > 0 && self.has_more(),
+            error: ptr::null_mut(),
+        }
+    }
+
+    /// Point count from section 3 headers; an upper bound when points are cropped or skipped
+    pub fn total_points(&self) -> usize {
+        self.messages
+            .iter()
+            .filter(|m| !m.duplicate)
+            .map(|m| m.num_points)
+            .sum()
+    }
+
+    /// Keep only messages whose first fixed surface is `surface_type` at one of
+    /// `values`, within `tolerance`; for freshly opened readers, nothing decoded yet
+    fn retain_levels(&mut self, surface_type: u8, values: &[f64], tolerance: f64) {
+        self.messages.retain(|m| {
+            m.surface_type == surface_type
+                && values
+                    .iter()
+                    .any(|v| (m.surface_value - v).abs() <= tolerance)
+        });
+    }
+
+    /// Mark the messages that repeat a field for the streams to leave out: same
+    /// parameter, levels and times, and the same ensemble member, statistical
+    /// processing, probability and percentile that tell otherwise equal fields
+    /// apart. Derived wind messages are never duplicates
+    fn set_dup_policy(&mut self, policy: Grib2DupPolicy) {
+        let field = |m: &ParsedMessage| {
+            (
+                parameters::key(m.discipline, m.parameter_category, m.parameter_number),
+                (m.surface_type, m.surface_value.to_bits()),
+                (m.surface_type_2, m.surface_value_2.to_bits()),
+                (m.reference_time, m.forecast_time),
+                (
+                    m.ensemble_member,
+                    m.statistical_process,
+                    m.time_range_seconds,
+                ),
+                (m.probability_type, m.threshold_lower.to_bits()),
+                (m.threshold_upper.to_bits(), m.percentile),
+            )
+        };
+        // Message kept for each field; later ones overwrite earlier ones for KeepLast
+        let mut kept = HashMap::new();
+        for (index, msg) in self.messages.iter().enumerate() {
+            if msg.derived.is_none() {
+                let slot = kept.entry(field(msg)).or_insert(index);
+                if policy == Grib2DupPolicy::KeepLast {
+                    *slot = index;
+                }
+            }
+        }
+        for (index, msg) in self.messages.iter_mut().enumerate() {
+            msg.duplicate = policy != Grib2DupPolicy::KeepAll
+                && msg.derived.is_none()
+                && kept[&field(msg)] != index;
+        }
+    }
+}
+
+// ============ C FFI Functions ============
+
+/// Version of the C ABI: the `#[repr(C)]` types and the signatures of the exported
+/// functions. Bump it with GRIB2_ABI_VERSION in grib2_ffi.h on any change to
+/// either, including appended struct fields; the extension refuses to load when
+/// the header it was compiled against disagrees with the linked library
+pub const GRIB2_ABI_VERSION: u32 = 5;
+
+/// The GRIB2_ABI_VERSION this library was built with
+#[no_mangle]
+pub extern "C" fn grib2_abi_version() -> u32 {
+    GRIB2_ABI_VERSION
+}
+
+/// Convert an error message into a C string owned by the caller
+/// Interior NUL bytes (e.g. from a path) are dropped rather than panicking across FFI
+fn error_string(message: impl Into<String>) -> *mut c_char {
+    let bytes: Vec<u8> = message
+        .into()
+        .into_bytes()
+        .into_iter()
+        .filter(|b| *b != 0)
+        .collect();
+    CString::new(bytes).unwrap_or_default().into_raw()
+}
+
+/// Run an FFI body, converting a panic into an error message instead of unwinding into C
+fn catch_panic<T>(body: impl FnOnce() -> T) -> Result<T, String> {
+    std::panic::catch_unwind(AssertUnwindSafe(body)).map_err(|payload| {
+        let detail = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        format!("Internal error: {}", detail)
+    })
+}
+
+/// Run `body` on a reader handle; a null handle or a panic yields `fallback`
+fn with_reader<T>(
+    reader: *mut Grib2Reader,
+    fallback: T,
+    body: impl FnOnce(&mut Grib2Reader) -> T,
+) -> T {
+    if reader.is_null() {
+        return fallback;
+    }
+    let reader = unsafe { &mut *reader };
+    catch_panic(|| body(reader)).unwrap_or(fallback)
+}
+
+/// Hand a reader to C, or store the failure in `error` and `error_code` (either may be null)
+fn into_handle(
+    result: Result<Grib2Reader, Grib2Error>,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    match result {
+        Ok(reader) => {
+            report_error(None, error, error_code);
+            Box::into_raw(Box::new(reader))
+        }
+        Err(e) => {
+            report_error(Some(e), error, error_code);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Fill the optional error out-params (either may be null); None reports success
+fn report_error(
+    result: Option<Grib2Error>,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) {
+    let (message, code) = match result {
+        None => (ptr::null_mut(), Grib2ErrorCode::Ok),
+        Some(e) => (error_string(e.message), e.code),
+    };
+    if error.is_null() {
+        grib2_free_error(message);
+    } else {
+        unsafe { *error = message; }
+    }
+    if !error_code.is_null() {
+        unsafe { *error_code = code; }
+    }
+}
+
+/// Shared body of the path-based open functions
+fn open_path(
+    path: *const c_char,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+    open: impl FnOnce(&str) -> Result<Grib2Reader, Grib2Error>,
+) -> *mut Grib2Reader {
+    let result = catch_panic(|| open(path_arg(path)?));
+    into_handle(flatten_panic(result), error, error_code)
+}
+
+/// Borrow a C path argument as UTF-8
+fn path_arg<'a>(path: *const c_char) -> Result<&'a str, Grib2Error> {
+    if path.is_null() {
+        return Err(Grib2Error::new(
+            Grib2ErrorCode::InvalidArgument,
+            "Null path",
+        ));
+    }
+    unsafe { CStr::from_ptr(path) }.to_str().map_err(|e| {
+        Grib2Error::new(
+            Grib2ErrorCode::InvalidArgument,
+            format!("Invalid UTF-8 in path: {}", e),
+        )
+    })
+}
+
+/// Merge a caught panic into a fallible result
+fn flatten_panic<T>(result: Result<Result<T, Grib2Error>, String>) -> Result<T, Grib2Error> {
+    result.unwrap_or_else(|panic| Err(Grib2Error::new(Grib2ErrorCode::Internal, panic)))
+}
+
+/// Open a GRIB2 file for streaming reads
+/// Returns opaque handle, caller must close with grib2_close
+#[no_mangle]
+pub extern "C" fn grib2_open(path: *const c_char) -> *mut Grib2Reader {
+    open_path(path, ptr::null_mut(), ptr::null_mut(), |path| {
+        Grib2Reader::open(path)
+    })
+}
+
+/// Open a GRIB2 file and get error message and code if failed
+/// The open functions taking `error` also take a nullable `error_code` out-param
+#[no_mangle]
+pub extern "C" fn grib2_open_with_error(
+    path: *const c_char,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    open_path(path, error, error_code, |path| Grib2Reader::open(path))
+}
+
+/// Open a GRIB2 file and decode all messages immediately on a thread pool
+/// Faster for files dominated by complex/JPEG2000 packing, but holds every point in memory
+#[cfg(feature = "parallel")]
+#[no_mangle]
+pub extern "C" fn grib2_open_parallel(
+    path: *const c_char,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    open_path(path, error, error_code, |path| {
+        let mut reader = Grib2Reader::open(path)?;
+        reader.decode_all_parallel();
+        Ok(reader)
+    })
+}
+
+/// Open a GRIB2 file, failing instead of skipping messages that can't be decoded
+/// The error names the first such message and why (unsupported grid or packing,
+/// corrupt data); costs one read of every message's data sections
+#[no_mangle]
+pub extern "C" fn grib2_open_strict(
+    path: *const c_char,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    open_path(path, error, error_code, |path| {
+        let reader = Grib2Reader::open(path)?;
+        reader.check_strict()?;
+        Ok(reader)
+    })
+}
+
+/// Open several files (e.g. one per forecast hour) as a single reader whose messages
+/// follow each other in path order; points and message info carry the file's
+/// position in source_index. When a file can't be opened, skip_failed = false fails
+/// with its error, while true leaves it out and lists it in grib2_get_skipped with
+/// reason GRIB2_SKIP_OPEN_FAILED. Fails when no file opens
+#[no_mangle]
+pub extern "C" fn grib2_open_multi(
+    paths: *const *const c_char,
+    count: usize,
+    skip_failed: bool,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    let result = catch_panic(|| {
+        if paths.is_null() || count == 0 {
+            return Err(Grib2Error::new(
+                Grib2ErrorCode::InvalidArgument,
+                "No paths given",
+            ));
+        }
+        let paths = unsafe { std::slice::from_raw_parts(paths, count) };
+        let paths = paths
+            .iter()
+            .map(|&path| path_arg(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        Grib2Reader::new_multi(&paths, skip_failed)
+    });
+    into_handle(flatten_panic(result), error, error_code)
+}
+
+/// Combine two readers into one that streams reader_a's messages, then reader_b's,
+/// e.g. U and V wind fetched by separate range requests, without a temporary file
+/// Both inputs are consumed: they are freed by the call and must not be used or
+/// closed afterwards; close only the returned reader. Messages keep their message
+/// numbers, and reader_b's source_index values move past reader_a's as with
+/// grib2_open_multi. The merged reader starts at the first point with reader_a's
+/// filter and settings; derived wind messages and duplicate marks are dropped, so
+/// call grib2_wind_derive or grib2_set_dup_policy on it again. Returns NULL,
+/// consuming neither, when either input is null or both are the same reader
+#[no_mangle]
+pub extern "C" fn grib2_merge(
+    reader_a: *mut Grib2Reader,
+    reader_b: *mut Grib2Reader,
+) -> *mut Grib2Reader {
+    if reader_a.is_null() || reader_b.is_null() || reader_a == reader_b {
+        return ptr::null_mut();
+    }
+    let (a, b) = unsafe { (Box::from_raw(reader_a), Box::from_raw(reader_b)) };
+    catch_panic(|| Box::into_raw(Box::new(a.merge(*b)))).unwrap_or(ptr::null_mut())
+}
+
+/// Open a local GRIB2 file through a memory map instead of buffered reads
+/// Avoids read syscalls on large local archives; requires the `mmap` feature
+#[cfg(feature = "mmap")]
+#[no_mangle]
+pub extern "C" fn grib2_open_mmap(
+    path: *const c_char,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    open_path(path, error, error_code, Grib2Reader::new_mmap)
+}
+
+/// Open a GRIB2 file, decoding only messages matching the given parameter
+/// Pass -1 for discipline, param_category or param_number to match any value
+/// Products without a category or number (e.g. 4.254 text) only match -1 there
+#[no_mangle]
+pub extern "C" fn grib2_open_with_filter(
+    path: *const c_char,
+    discipline: c_int,
+    param_category: c_int,
+    param_number: c_int,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    let filter = MessageFilter::from_c(discipline, param_category, param_number);
+    open_path(path, error, error_code, |path| {
+        Grib2Reader::new_filtered(path, &filter)
+    })
+}
+
+/// Open a GRIB2 file holding only the first field matching the given parameter
+/// Pass -1 for discipline, param_category or param_number to match any value
+/// Products without a category or number (e.g. 4.254 text) only match -1 there
+/// Messages are matched from their section headers and the file is read no further
+/// than the one holding the field, so this is the quickest way to pull one field
+/// out of a large file. Fails with NotFound when no field matches, and with the
+/// message's own error when the one holding the match can't be read
+#[no_mangle]
+pub extern "C" fn grib2_open_first_match(
+    path: *const c_char,
+    discipline: c_int,
+    param_category: c_int,
+    param_number: c_int,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    let filter = MessageFilter::from_c(discipline, param_category, param_number);
+    open_path(path, error, error_code, |path| {
+        Grib2Reader::first_match(path, &filter)
+    })
+}
+
+/// Open a GRIB2 file keeping only points inside the bounding box
+/// min_lon > max_lon selects a box wrapping across the antimeridian
+#[no_mangle]
+pub extern "C" fn grib2_open_with_bbox(
+    path: *const c_char,
+    min_lat: c_double,
+    min_lon: c_double,
+    max_lat: c_double,
+    max_lon: c_double,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    let filter = MessageFilter {
+        bbox: Some(BoundingBox::new(min_lat, min_lon, max_lat, max_lon)),
+        ..MessageFilter::default()
+    };
+    open_path(path, error, error_code, |path| {
+        Grib2Reader::new_filtered(path, &filter)
+    })
+}
+
+/// Open a GRIB2 file keeping only messages with a forecast time in
+/// [min_forecast_sec, max_forecast_sec]; other messages are never decoded
+#[no_mangle]
+pub extern "C" fn grib2_open_with_time_filter(
+    path: *const c_char,
+    min_forecast_sec: i64,
+    max_forecast_sec: i64,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    let filter = MessageFilter {
+        lead_time: Some((min_forecast_sec, max_forecast_sec)),
+        ..MessageFilter::default()
+    };
+    open_path(path, error, error_code, |path| {
+        Grib2Reader::new_filtered(path, &filter)
+    })
+}
+
+/// Open a GRIB2 file keeping only messages valid in [min_valid_time, max_valid_time]
+/// Valid time is the reference time plus the forecast time, in unix seconds
+#[no_mangle]
+pub extern "C" fn grib2_open_with_valid_time_filter(
+    path: *const c_char,
+    min_valid_time: i64,
+    max_valid_time: i64,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    let filter = MessageFilter {
+        valid_time: Some((min_valid_time, max_valid_time)),
+        ..MessageFilter::default()
+    };
+    open_path(path, error, error_code, |path| {
+        Grib2Reader::new_filtered(path, &filter)
+    })
+}
+
+/// Open a GRIB2 file keeping only messages whose first fixed surface is of
+/// `surface_type` (code table 4.5, e.g. 100 isobaric) with a value within `tolerance`
+/// of one of `values`, in the same units as surface_value (Pa for isobaric levels)
+/// Other messages are dropped once the headers are indexed, before any is decoded
+#[no_mangle]
+pub extern "C" fn grib2_open_with_level_filter(
+    path: *const c_char,
+    surface_type: u8,
+    values: *const c_double,
+    value_count: usize,
+    tolerance: c_double,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    open_path(path, error, error_code, |path| {
+        if values.is_null() || value_count == 0 {
+            return Err(Grib2Error::new(
+                Grib2ErrorCode::InvalidArgument,
+                "No level values given",
+            ));
+        }
+        let values = unsafe { std::slice::from_raw_parts(values, value_count) };
+        let mut reader = Grib2Reader::open(path)?;
+        reader.retain_levels(surface_type, values, tolerance);
+        Ok(reader)
+    })
+}
+
+/// Open a GRIB2 reader from in-memory bytes (for HTTP fetched data)
+/// Returns opaque handle, caller must close with grib2_close
+#[no_mangle]
+pub extern "C" fn grib2_open_from_bytes(
+    data: *const u8,
+    len: usize,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    if data.is_null() || len == 0 {
+        let empty = Grib2Error::new(Grib2ErrorCode::InvalidArgument, "Empty or null data");
+        return into_handle(Err(empty), error, error_code);
+    }
+
+    let bytes = unsafe { std::slice::from_raw_parts(data, len) };
+    let result = catch_panic(|| Grib2Reader::from_bytes(bytes));
+    into_handle(flatten_panic(result), error, error_code)
+}
+
+/// Open a GRIB2 reader over in-memory bytes without copying them
+///
+/// Safety contract: `data` must stay valid and unmodified until grib2_close returns for
+/// this handle. The reader keeps pointing into the buffer for lazy decoding, so freeing
+/// it earlier is a use-after-free. Prefer grib2_open_from_bytes when that can't be
+/// guaranteed; it copies the buffer and owns the copy.
+#[no_mangle]
+pub extern "C" fn grib2_open_from_bytes_borrowed(
+    data: *const u8,
+    len: usize,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    if data.is_null() || len == 0 {
+        let empty = Grib2Error::new(Grib2ErrorCode::InvalidArgument, "Empty or null data");
+        return into_handle(Err(empty), error, error_code);
+    }
+
+    let bytes: &'static [u8] = unsafe { std::slice::from_raw_parts(data, len) };
+    let result = catch_panic(|| Grib2Reader::from_borrowed_bytes(bytes));
+    into_handle(flatten_panic(result), error, error_code)
+}
+
+/// Open a GRIB2 reader over the host's I/O, e.g. DuckDB's FileSystem with its
+/// encrypted and remote backends, instead of a path the library opens itself
+/// Both callbacks get `user_data`, which (like the callbacks) must stay valid until
+/// grib2_close returns: messages are read lazily while streaming, possibly from
+/// whichever thread uses the reader. A negative return from either callback fails
+/// the open with an IoError, or skips the message being decoded
+/// Returns opaque handle, caller must close with grib2_close
+#[no_mangle]
+pub extern "C" fn grib2_open_from_callback(
+    read_fn: Option<Grib2ReadCallback>,
+    seek_fn: Option<Grib2SeekCallback>,
+    user_data: *mut c_void,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    let (Some(read), Some(seek)) = (read_fn, seek_fn) else {
+        let missing = Grib2Error::new(
+            Grib2ErrorCode::InvalidArgument,
+            "Null read or seek callback",
+        );
+        return into_handle(Err(missing), error, error_code);
+    };
+    let source = CallbackSource {
+        read,
+        seek,
+        user_data,
+    };
+    let result = catch_panic(|| Grib2Reader::from_callbacks(source));
+    into_handle(flatten_panic(result), error, error_code)
+}
+
+/// Open a GRIB2 file or byte range over HTTP(S), e.g. one message located via a .idx file
+/// byte_length 0 reads from byte_offset to the end; HTTP failures are reported as
+/// "HTTP error: ..." and decoding failures as "Failed to parse GRIB: ..."
+#[cfg(feature = "remote")]
+#[no_mangle]
+pub extern "C" fn grib2_open_from_url(
+    url: *const c_char,
+    byte_offset: u64,
+    byte_length: u64,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> *mut Grib2Reader {
+    open_path(url, error, error_code, |url| {
+        Grib2Reader::from_url(url, byte_offset, byte_length)
+    })
+}
+
+/// Read a batch of data points (up to max_count)
+/// Caller must free batch with grib2_free_batch; loops reading many small batches
+/// should prefer grib2_read_batch_into, which allocates nothing per call
+#[no_mangle]
+pub extern "C" fn grib2_read_batch(reader: *mut Grib2Reader, max_count: usize) -> Grib2Batch {
+    if reader.is_null() {
+        return Grib2Batch::failed("Null reader");
+    }
+
+    let reader = unsafe { &mut *reader };
+    catch_panic(|| reader.read_batch(max_count)).unwrap_or_else(Grib2Batch::failed)
+}
+
+/// Read up to capacity points into a buffer owned (and reused) by the caller
+/// out_count receives the number written and out_has_more whether points remain,
+/// as in Grib2Batch; false for a null reader or buffer
+#[no_mangle]
+pub extern "C" fn grib2_read_batch_into(
+    reader: *mut Grib2Reader,
+    buffer: *mut Grib2DataPoint,
+    capacity: usize,
+    out_count: *mut usize,
+    out_has_more: *mut bool,
+) -> bool {
+    if buffer.is_null() && capacity > 0 {
+        return false;
+    }
+    let result = with_reader(reader, None, |reader| {
+        let mut count = 0;
+        reader.visit_points(capacity, |msg, point| {
+            // The buffer may be uninitialized, so write without dropping old contents
+            unsafe {
+                buffer.add(count).write(Grib2DataPoint::new(msg, point));
+            }
+            count += 1;
+            true
+        });
+        Some((count, count > 0 && reader.has_more()))
+    });
+    let Some((count, has_more)) = result else {
+        return false;
+    };
+    unsafe {
+        if !out_count.is_null() {
+            *out_count = count;
+        }
+        if !out_has_more.is_null() {
+            *out_has_more = has_more;
+        }
+    }
+    true
+}
+
+/// Return the points the next grib2_read_batch would, without consuming them
+/// Free the batch with grib2_free_batch; a peek in the middle of a lazily decoded
+/// message costs decoding the message up to the cursor again
+#[no_mangle]
+pub extern "C" fn grib2_peek_batch(reader: *mut Grib2Reader, max_count: usize) -> Grib2Batch {
+    if reader.is_null() {
+        return Grib2Batch::failed("Null reader");
+    }
+
+    let reader = unsafe { &mut *reader };
+    catch_panic(|| reader.peek_batch(max_count)).unwrap_or_else(Grib2Batch::failed)
+}
+
+/// Read a batch of data points with unit conversions applied (up to max_count)
+/// unit_mode is a mask of GRIB2_UNITS_* flags; parameters without a mapping keep
+/// their native units, and 0 behaves like grib2_read_batch
+/// Caller must free batch with grib2_free_batch
+#[no_mangle]
+pub extern "C" fn grib2_read_batch_converted(
+    reader: *mut Grib2Reader,
+    max_count: usize,
+    unit_mode: u32,
+) -> Grib2Batch {
+    if reader.is_null() {
+        return Grib2Batch::failed("Null reader");
+    }
+
+    let reader = unsafe { &mut *reader };
+    catch_panic(|| reader.read_batch_converted(max_count, unit_mode))
+        .unwrap_or_else(Grib2Batch::failed)
+}
+
+/// Read a batch of single-precision data points (up to max_count)
+/// Caller must free batch with grib2_free_batch_f32
+#[no_mangle]
+pub extern "C" fn grib2_read_batch_f32(
+    reader: *mut Grib2Reader,
+    max_count: usize,
+) -> Grib2BatchF32 {
+    if reader.is_null() {
+        return Grib2BatchF32::failed("Null reader");
+    }
+
+    let reader = unsafe { &mut *reader };
+    catch_panic(|| reader.read_batch_f32(max_count)).unwrap_or_else(Grib2BatchF32::failed)
+}
+
+/// Read a batch of data points (up to max_count) with the integer each value was
+/// packed as in section 7, and whether the parameter is categorical (its units
+/// are a code table, as for precipitation type). Packings without linear integers
+/// (IEEE floats, 5.61 logarithmic) and derived wind give the value rounded
+/// Caller must free batch with grib2_free_batch_raw
+#[no_mangle]
+pub extern "C" fn grib2_read_batch_raw(
+    reader: *mut Grib2Reader,
+    max_count: usize,
+) -> Grib2BatchRaw {
+    if reader.is_null() {
+        return Grib2BatchRaw::failed("Null reader");
+    }
+
+    let reader = unsafe { &mut *reader };
+    catch_panic(|| reader.read_batch_raw(max_count)).unwrap_or_else(Grib2BatchRaw::failed)
+}
+
+/// Read a batch of data points (up to max_count) with the H3 cell of each point
+/// at the given resolution (0-15)
+/// Caller must free batch with grib2_free_batch_h3
+#[cfg(feature = "h3")]
+#[no_mangle]
+pub extern "C" fn grib2_read_batch_h3(
+    reader: *mut Grib2Reader,
+    max_count: usize,
+    resolution: u8,
+) -> Grib2BatchH3 {
+    if reader.is_null() {
+        return Grib2BatchH3::failed("Null reader");
+    }
+    let resolution = match h3o::Resolution::try_from(resolution) {
+        Ok(resolution) => resolution,
+        Err(_) => return Grib2BatchH3::failed(format!("Invalid H3 resolution: {resolution}")),
+    };
+
+    let reader = unsafe { &mut *reader };
+    catch_panic(|| reader.read_batch_h3(max_count, resolution)).unwrap_or_else(Grib2BatchH3::failed)
+}
+
+/// Called by grib2_for_each_point with each point and the caller's user_data
+/// The point is only valid during the call; return false to stop the iteration
+pub type Grib2PointCallback =
+    extern "C" fn(point: *const Grib2DataPoint, user_data: *mut c_void) -> bool;
+
+/// Stream points from the cursor straight into a callback, without allocating batches
+/// Stops after the callback returns false or the last point; returns how many points
+/// were delivered. The cursor moves past every delivered point, so a later call or
+/// grib2_read_batch picks up after the point that stopped the iteration
+/// Like the read functions it stops at the point budget; grib2_budget_exceeded
+/// then tells whether points were left out
+/// The callback must not unwind (e.g. throw a C++ exception) through this function
+#[no_mangle]
+pub extern "C" fn grib2_for_each_point(
+    reader: *mut Grib2Reader,
+    callback: Option<Grib2PointCallback>,
+    user_data: *mut c_void,
+) -> usize {
+    let Some(callback) = callback else {
+        return 0;
+    };
+    with_reader(reader, 0, |reader| {
+        reader.visit_points(usize::MAX, |msg, point| {
+            let point = Grib2DataPoint::new(msg, point);
+            callback(&point, user_data)
+        })
+    })
+}
+
+/// Write every point of the reader to a CSV file with a header row:
+/// latitude,longitude,value,discipline,category,number,reference_time,
+/// forecast_time,surface_type,surface_value,message_index
+/// Rewinds the reader first and leaves it at the end; reference times are ISO 8601
+/// UTC. On failure error (if not null) receives a message for grib2_free_error
+/// Returns false when a point budget (grib2_set_point_budget) stops the export
+/// early, leaving a file with only the points within the budget
+#[no_mangle]
+pub extern "C" fn grib2_write_csv(
+    reader: *mut Grib2Reader,
+    path: *const c_char,
+    error: *mut *mut c_char,
+) -> bool {
+    let result = if reader.is_null() {
+        Err(Grib2Error::new(
+            Grib2ErrorCode::InvalidArgument,
+            "Null reader",
+        ))
+    } else {
+        path_arg(path).and_then(|path| {
+            let file =
+                File::create(path).map_err(|e| Grib2Error::io("Failed to create file", e))?;
+            let fallback = Err(Grib2Error::new(
+                Grib2ErrorCode::Internal,
+                "Panic while writing CSV",
+            ));
+            with_reader(reader, fallback, |reader| {
+                reader
+                    .write_csv(file)
+                    .map_err(|e| Grib2Error::io("Failed to write CSV", e))
+            })
+        })
+    };
+    let ok = result.is_ok();
+    report_error(result.err(), error, ptr::null_mut());
+    ok
+}
+
+/// Write one message as a single-band float32 GeoTIFF on WGS 84 longitudes and
+/// latitudes, rows north to south whatever the scanning mode; masked points are
+/// NaN, also declared as the nodata value. The whole grid is written, ignoring
+/// the bbox and missing-value policy, and the reader's cursor does not move
+/// Only regular lat/lon grids (template 3.0) are supported. On failure error (if
+/// not null) receives a message for grib2_free_error
+#[cfg(feature = "geotiff")]
+#[no_mangle]
+pub extern "C" fn grib2_write_geotiff(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+    path: *const c_char,
+    error: *mut *mut c_char,
+) -> bool {
+    let result = if reader.is_null() {
+        Err(Grib2Error::new(
+            Grib2ErrorCode::InvalidArgument,
+            "Null reader",
+        ))
+    } else {
+        path_arg(path).and_then(|path| {
+            let fallback = Err(Grib2Error::new(
+                Grib2ErrorCode::Internal,
+                "Panic while writing GeoTIFF",
+            ));
+            with_reader(reader, fallback, |reader| {
+                reader.write_geotiff(message_index, Path::new(path))
+            })
+        })
+    };
+    let ok = result.is_ok();
+    report_error(result.err(), error, ptr::null_mut());
+    ok
+}
+
+/// Choose whether bitmap-masked points are skipped (true) or emitted as NaN
+/// with is_missing set (false, the default)
+/// Applies to messages decoded after the call
+#[no_mangle]
+pub extern "C" fn grib2_set_skip_missing(reader: *mut Grib2Reader, skip: bool) {
+    with_reader(reader, (), |reader| reader.filter.skip_missing = skip);
+}
+
+/// Choose whether global lat/lon grids whose last column repeats the first (e.g.
+/// both 0° and 360°) drop that column (true) or emit it (false, the default), so
+/// aggregations don't count the seam twice. Applies to messages decoded after the call
+#[no_mangle]
+pub extern "C" fn grib2_set_dedup_wraparound(reader: *mut Grib2Reader, enabled: bool) {
+    with_reader(reader, (), |reader| {
+        reader.filter.dedup_wraparound = enabled
+    });
+}
+
+/// Choose the range emitted longitudes are reported in: [-180, 180) (Signed, the
+/// default) or [0, 360) (Unsigned). Bounding boxes are always given in [-180, 180)
+/// Applies to messages decoded after the call
+#[no_mangle]
+pub extern "C" fn grib2_set_lon_convention(reader: *mut Grib2Reader, mode: Grib2LonConvention) {
+    with_reader(reader, (), |reader| reader.filter.lon_convention = mode);
+}
+
+/// Choose what streams when several messages hold the same field (parameter,
+/// levels, reference and forecast time, ensemble member and processing), as in
+/// files carrying a revised field next to the original: KeepAll (the default),
+/// KeepFirst or KeepLast in file order. Left-out messages stream no points and
+/// drop out of grib2_total_points but keep their positions, so message getters
+/// and point queries still reach them. Applies from the next message read
+#[no_mangle]
+pub extern "C" fn grib2_set_dup_policy(reader: *mut Grib2Reader, policy: Grib2DupPolicy) {
+    with_reader(reader, (), |reader| reader.set_dup_policy(policy));
+}
+
+/// Emit each message's points sorted north to south, then west to east in the
+/// longitude convention, instead of in the grid's scanning order (default false).
+/// Global grids starting at 0° otherwise jump from 180° back to -180° mid-row
+/// Costs a sort per message and holds its points in memory while it streams;
+/// applies to messages decoded after the call
+#[no_mangle]
+pub extern "C" fn grib2_set_sort_by_coordinate(reader: *mut Grib2Reader, sort: bool) {
+    with_reader(reader, (), |reader| reader.filter.sort_by_coordinate = sort);
+}
+
+/// Keep batches from splitting grid rows (default false): a batch ends early rather
+/// than cut a row, so it may hold fewer than max_count points, and points come in
+/// the grid's scanning order, overriding grib2_set_sort_by_coordinate. A row is Ni
+/// points, or Nj when the grid scans along meridians, less any dropped by the
+/// bbox or missing-value policy; a row longer than max_count is still split.
+/// Grids without fixed rows (reduced, unstructured) batch as before. Applies to
+/// messages decoded after the call
+#[no_mangle]
+pub extern "C" fn grib2_set_row_aligned(reader: *mut Grib2Reader, enabled: bool) {
+    with_reader(reader, (), |reader| reader.filter.row_aligned = enabled);
+}
+
+/// Forward diagnostics to a C host: the templates of every message at open
+/// (Debug) and each message skipped or failing to decode, with why (Warn).
+/// Process-wide, replacing any earlier callback; pass NULL to stop. Events also
+/// go to `tracing` for Rust hosts. The callback may run on any thread that uses
+/// a reader and must not unwind
+#[cfg(feature = "tracing")]
+#[no_mangle]
+pub extern "C" fn grib2_set_log_callback(
+    callback: Option<Grib2LogCallback>,
+    user_data: *mut c_void,
+) {
+    log::set_callback(callback, user_data);
+}
+
+/// Keep up to `bytes` of unpacked values in a process-wide LRU cache, so readers
+/// opened later on the same file (same path, size and modification time) stream
+/// and query its messages without unpacking them again; the headers are still
+/// parsed at open. 0 turns caching off and empties the cache (the default)
+/// Only files opened by path are cached; grib2_open_parallel decodes without it
+#[no_mangle]
+pub extern "C" fn grib2_set_cache_capacity(bytes: usize) {
+    cache::set_capacity(bytes);
+}
+
+/// Rewind the reader so the next read_batch starts again at the first point
+/// The parsed message index is kept, so this does not re-read the file headers
+#[no_mangle]
+pub extern "C" fn grib2_reset(reader: *mut Grib2Reader) {
+    with_reader(reader, (), |reader| reader.reset());
+}
+
+/// Cap the points the reader hands out between grib2_reset calls, across all read
+/// functions; 0 removes the cap (the default). Once it is reached reading stops
+/// with has_more false and grib2_budget_exceeded reports whether points were left
+/// out. Bounds what callers accumulate from a huge file; messages decoded up front
+/// by grib2_open_parallel are still held in full
+#[no_mangle]
+pub extern "C" fn grib2_set_point_budget(reader: *mut Grib2Reader, max_total_points: usize) {
+    with_reader(reader, (), |reader| {
+        reader.point_budget = match max_total_points {
+            0 => usize::MAX,
+            n => n,
+        }
+    });
+}
+
+/// Whether the point budget cut the output short: it was reached with points
+/// still unread, so what was read is partial
+#[no_mangle]
+pub extern "C" fn grib2_budget_exceeded(reader: *mut Grib2Reader) -> bool {
+    with_reader(reader, false, |reader| reader.budget_exceeded())
+}
+
+/// Advance the cursor by n points across message boundaries without returning them,
+/// e.g. for an OFFSET; returns how many points were skipped, fewer than n only once
+/// the reader runs out (has_more is then false). Unfiltered messages that are skipped
+/// entirely are not decoded, so they count as their header point count
+#[no_mangle]
+pub extern "C" fn grib2_skip_points(reader: *mut Grib2Reader, n: usize) -> usize {
+    with_reader(reader, 0, |reader| reader.skip_points(n))
+}
+
+/// Continue streaming from the first point of a message (0-based, as in grib2_list_messages)
+/// Returns false and leaves the reader untouched if the index is out of range
+#[no_mangle]
+pub extern "C" fn grib2_seek_message(reader: *mut Grib2Reader, message_index: usize) -> bool {
+    with_reader(reader, false, |reader| reader.seek_message(message_index))
+}
+
+/// Get the stream position: the message under the cursor and how many of its
+/// points were read, after the bbox and missing-value filters. Pass the pair to
+/// grib2_seek to resume there, e.g. from a checkpoint. At the end of the stream
+/// the message is grib2_message_count and the point 0. False for a null reader
+#[no_mangle]
+pub extern "C" fn grib2_tell(
+    reader: *mut Grib2Reader,
+    out_message: *mut usize,
+    out_point: *mut usize,
+) -> bool {
+    let Some((message, point)) = with_reader(reader, None, |reader| {
+        Some((reader.current_message, reader.current_point))
+    }) else {
+        return false;
+    };
+    unsafe {
+        if !out_message.is_null() {
+            *out_message = message;
+        }
+        if !out_point.is_null() {
+            *out_point = point;
+        }
+    }
+    true
+}
+
+/// Continue streaming at a position from grib2_tell. The message is re-decoded up
+/// to the point, so later reads return the same points as before the checkpoint
+/// Returns false and leaves the position unchanged when the message is out of
+/// range or has fewer points than `point`
+#[no_mangle]
+pub extern "C" fn grib2_seek(reader: *mut Grib2Reader, message: usize, point: usize) -> bool {
+    with_reader(reader, false, |reader| reader.seek(message, point))
+}
+
+/// Count the messages of a file and sum their point counts from the section 3 headers
+/// Nothing is decoded, so this is a cheap cardinality estimate before a full open;
+/// quasi-regular grids report the total of their per-row point list
+/// Counts every field, including ones a later open would skip
+#[no_mangle]
+pub extern "C" fn grib2_inspect(
+    path: *const c_char,
+    out_message_count: *mut usize,
+    out_total_points: *mut usize,
+    error: *mut *mut c_char,
+    error_code: *mut Grib2ErrorCode,
+) -> bool {
+    let result = catch_panic(|| {
+        let file =
+            File::open(path_arg(path)?).map_err(|e| Grib2Error::io("Failed to open file", e))?;
+        let mut source = decompressed(BufReader::new(file))?;
+        check_complete(source.as_mut())?;
+        let grib2 = grib::from_reader(source)?;
+        let (mut messages, mut points) = (0, 0);
+        for (_, submessage) in grib2.iter() {
+            messages += 1;
+            points += submessage.grid_def().num_points() as usize;
+        }
+        Ok((messages, points))
+    });
+    match flatten_panic(result) {
+        Ok((messages, points)) => {
+            unsafe {
+                if !out_message_count.is_null() {
+                    *out_message_count = messages;
+                }
+                if !out_total_points.is_null() {
+                    *out_total_points = points;
+                }
+            }
+            report_error(None, error, error_code);
+            true
+        }
+        Err(e) => {
+            report_error(Some(e), error, error_code);
+            false
+        }
+    }
+}
+
+/// Get total number of data points in file (for cardinality)
+#[no_mangle]
+pub extern "C" fn grib2_total_points(reader: *mut Grib2Reader) -> usize {
+    with_reader(reader, 0, |reader| reader.total_points())
+}
+
+/// Estimate the memory the reader's points take once read: grib2_total_points times
+/// the size of Grib2DataPointF32 (single_precision) or Grib2DataPoint. For sizing
+/// buffers and progress totals; bbox and missing-value filters may emit fewer
+#[no_mangle]
+pub extern "C" fn grib2_estimate_bytes(reader: *mut Grib2Reader, single_precision: bool) -> usize {
+    let point_size = match single_precision {
+        true => std::mem::size_of::<Grib2DataPointF32>(),
+        false => std::mem::size_of::<Grib2DataPoint>(),
+    };
+    with_reader(reader, 0, |reader| {
+        reader.total_points().saturating_mul(point_size)
+    })
+}
+
+/// Get how many points the read functions handed out since open or the last
+/// grib2_reset; points passed over by grib2_skip_points or a seek don't count
+#[no_mangle]
+pub extern "C" fn grib2_points_consumed(reader: *mut Grib2Reader) -> usize {
+    with_reader(reader, 0, |reader| reader.emitted)
+}
+
+/// Get the number of messages available to the reader
+/// Message getters take a 0-based position below this count
+#[no_mangle]
+pub extern "C" fn grib2_message_count(reader: *mut Grib2Reader) -> usize {
+    with_reader(reader, 0, |reader| reader.messages.len())
+}
+
+/// List the metadata of every message without decoding values
+/// The array is indexed like other message getters; free it with grib2_free_message_list
+#[no_mangle]
+pub extern "C" fn grib2_list_messages(
+    reader: *mut Grib2Reader,
+    out_array: *mut *mut Grib2MessageInfo,
+    out_count: *mut usize,
+) -> bool {
+    if out_array.is_null() || out_count.is_null() {
+        return false;
+    }
+    with_reader(reader, false, |reader| {
+        let infos: Vec<_> = reader.messages().collect();
+        let (data, count) = leak_vec(infos);
+        unsafe {
+            *out_array = data;
+            *out_count = count;
+        }
+        true
+    })
+}
+
+/// Free an array returned by grib2_list_messages
+#[no_mangle]
+pub extern "C" fn grib2_free_message_list(array: *mut Grib2MessageInfo, count: usize) {
+    if !array.is_null() {
+        unsafe {
+            let _ = Vec::from_raw_parts(array, count, count);
+        }
+    }
+}
+
+/// List the messages that produced no points: dropped at open for an unsupported grid,
+/// or failed to decode while streaming (only messages streamed so far are covered)
+/// Free the array with grib2_free_skipped
+#[no_mangle]
+pub extern "C" fn grib2_get_skipped(
+    reader: *mut Grib2Reader,
+    out_array: *mut *mut Grib2SkippedMessage,
+    out_count: *mut usize,
+) -> bool {
+    if out_array.is_null() || out_count.is_null() {
+        return false;
+    }
+    with_reader(reader, false, |reader| {
+        let entries: Vec<_> = reader
+            .skipped
+            .iter()
+            .map(|skipped| Grib2SkippedMessage {
+                message_index: skipped.message_index,
+                submessage_index: skipped.submessage_index,
+                reason: skipped.reason,
+                error: error_string(skipped.error.as_str()),
+                source_index: skipped.source_index,
+            })
+            .collect();
+        let (data, count) = leak_vec(entries);
+        unsafe {
+            *out_array = data;
+            *out_count = count;
+        }
+        true
+    })
+}
+
+/// Free an array returned by grib2_get_skipped, including its error strings
+#[no_mangle]
+pub extern "C" fn grib2_free_skipped(array: *mut Grib2SkippedMessage, count: usize) {
+    if array.is_null() {
+        return;
+    }
+    let entries = unsafe { Vec::from_raw_parts(array, count, count) };
+    for entry in entries {
+        grib2_free_error(entry.error);
+    }
+}
+
+/// Get min/max/mean and count of the non-missing values of a message
+/// Computed once per message and cached; with a bbox only points inside it count
+/// Returns false for unknown or undecodable messages; min/max/mean are NaN when count is 0
+#[no_mangle]
+pub extern "C" fn grib2_message_stats(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+    out_min: *mut c_double,
+    out_max: *mut c_double,
+    out_count: *mut usize,
+    out_mean: *mut c_double,
+) -> bool {
+    let stats = match with_reader(reader, None, |reader| reader.message_stats(message_index)) {
+        Some(stats) => stats,
+        None => return false,
+    };
+    unsafe {
+        if !out_min.is_null() {
+            *out_min = stats.min;
+        }
+        if !out_max.is_null() {
+            *out_max = stats.max;
+        }
+        if !out_count.is_null() {
+            *out_count = stats.count;
+        }
+        if !out_mean.is_null() {
+            *out_mean = stats.mean;
+        }
+    }
+    true
+}
+
+/// Append computed wind speed (0/2/1, m s-1) and direction (0/2/0, degrees the
+/// wind blows from) messages for each U/V pair at the same level, time and grid
+/// The count pairs land at out_speed_msg.. and out_dir_msg.. after the file's own
+/// messages; they stream and query like any other message. Directions are
+/// relative to the grid axes when the components are (e.g. Lambert grids)
+/// Returns the number of pairs; a second call returns the first call's messages
+#[no_mangle]
+pub extern "C" fn grib2_wind_derive(
+    reader: *mut Grib2Reader,
+    out_speed_msg: *mut usize,
+    out_dir_msg: *mut usize,
+) -> usize {
+    let wind = match with_reader(reader, None, |reader| Some(reader.derive_wind())) {
+        Some(wind) => wind,
+        None => return 0,
+    };
+    unsafe {
+        if !out_speed_msg.is_null() {
+            *out_speed_msg = wind.speed;
+        }
+        if !out_dir_msg.is_null() {
+            *out_dir_msg = wind.direction;
+        }
+    }
+    wind.count
+}
+
+/// Number of grid points in one message (from section 3, before any bbox crop)
+/// 0 for a null reader or an index past grib2_message_count
+#[no_mangle]
+pub extern "C" fn grib2_message_point_count(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+) -> usize {
+    with_reader(reader, 0, |reader| {
+        reader
+            .messages
+            .get(message_index)
+            .map(|m| m.num_points)
+            .unwrap_or(0)
+    })
+}
+
+/// Get the number of fields (submessages) in the GRIB message holding a message,
+/// counting fields the filter left out or that were skipped at open; with the
+/// message_index/submessage_index pair of Grib2MessageInfo and
+/// grib2_find_submessage, callers can walk the fields of one envelope
+/// Returns 0 for unknown messages
+#[no_mangle]
+pub extern "C" fn grib2_submessage_count(reader: *mut Grib2Reader, message_index: usize) -> usize {
+    with_reader(reader, 0, |reader| {
+        reader
+            .messages
+            .get(message_index)
+            .map_or(0, |m| m.submessage_count as usize)
+    })
+}
+
+/// Find the message for a field given by its source (0 unless grib2_open_multi),
+/// GRIB message and submessage numbers, as in Grib2MessageInfo
+/// Returns false when that field was filtered out, skipped at open or doesn't exist
+#[no_mangle]
+pub extern "C" fn grib2_find_submessage(
+    reader: *mut Grib2Reader,
+    source_index: u32,
+    message_number: u32,
+    submessage_index: u32,
+    out_message_index: *mut usize,
+) -> bool {
+    let Some(index) = with_reader(reader, None, |reader| {
+        reader.find_submessage(source_index, message_number, submessage_index)
+    }) else {
+        return false;
+    };
+    if !out_message_index.is_null() {
+        unsafe { *out_message_index = index };
+    }
+    true
+}
+
+/// Get the reference time of a message as unix seconds (0 if unknown)
+#[no_mangle]
+pub extern "C" fn grib2_get_reference_time(reader: *mut Grib2Reader, message_index: usize) -> i64 {
+    with_reader(reader, 0, |reader| {
+        reader
+            .messages
+            .get(message_index)
+            .map(|m| m.reference_time)
+            .unwrap_or(0)
+    })
+}
+
+/// Get the significance of the reference time (code table 1.2)
+/// 0 = analysis, 1 = start of forecast, 255 = missing or unknown message
+#[no_mangle]
+pub extern "C" fn grib2_get_reference_time_significance(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+) -> u8 {
+    with_reader(reader, 255, |reader| {
+        reader
+            .messages
+            .get(message_index)
+            .map(|m| m.identification.reference_time_significance)
+            .unwrap_or(255)
+    })
+}
+
+/// Get the production status of a message from section 1 (code table 1.3)
+/// 0 = operational, 1 = operational test, 2 = research, 3 = re-analysis,
+/// 255 = missing or unknown message
+#[no_mangle]
+pub extern "C" fn grib2_get_production_status(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+) -> u8 {
+    with_reader(reader, 255, |reader| {
+        reader
+            .messages
+            .get(message_index)
+            .map(|m| m.identification.production_status)
+            .unwrap_or(255)
+    })
+}
+
+/// Get the type of data of a message from section 1 (code table 1.4)
+/// 0 = analysis, 1 = forecast, 2 = analysis and forecast, 3 = control forecast,
+/// 4 = perturbed forecast, 255 = missing or unknown message
+#[no_mangle]
+pub extern "C" fn grib2_get_data_type(reader: *mut Grib2Reader, message_index: usize) -> u8 {
+    with_reader(reader, 255, |reader| {
+        reader
+            .messages
+            .get(message_index)
+            .map(|m| m.identification.data_type)
+            .unwrap_or(255)
+    })
+}
+
+/// Get the originating centre (common code table C-11, e.g. 7 = NCEP, 78 = DWD,
+/// 98 = ECMWF) and sub-centre of a message from section 1; 65535 means missing
+/// Returns false for unknown messages
+#[no_mangle]
+pub extern "C" fn grib2_get_originating_centre(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+    out_centre: *mut u16,
+    out_subcentre: *mut u16,
+) -> bool {
+    let Some((centre, subcentre)) = with_reader(reader, None, |reader| {
+        reader
+            .messages
+            .get(message_index)
+            .map(|m| (m.identification.centre, m.identification.subcentre))
+    }) else {
+        return false;
+    };
+    unsafe {
+        if !out_centre.is_null() {
+            *out_centre = centre;
+        }
+        if !out_subcentre.is_null() {
+            *out_subcentre = subcentre;
+        }
+    }
+    true
+}
+
+/// Copy the whole identification section (section 1) of a message into `out`:
+/// centre, table versions, reference time components, production status and data
+/// type in one call; read at open. Returns false for unknown messages
+#[no_mangle]
+pub extern "C" fn grib2_get_identification(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+    out: *mut Grib2Identification,
+) -> bool {
+    let Some(identification) = with_reader(reader, None, |reader| {
+        reader.messages.get(message_index).map(|m| m.identification)
+    }) else {
+        return false;
+    };
+    if !out.is_null() {
+        unsafe { *out = identification };
+    }
+    true
+}
+
+/// Get the generating process identifier of a message from section 4, assigned by
+/// the originating centre to each of its models
+/// 255 = missing, a template without the field, or an unknown message
+#[no_mangle]
+pub extern "C" fn grib2_get_generating_process(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+) -> u8 {
+    with_reader(reader, 255, |reader| {
+        reader
+            .messages
+            .get(message_index)
+            .map(|m| m.generating_process)
+            .unwrap_or(255)
+    })
+}
+
+/// Get the data representation template (section 5, e.g. 0 = simple, 3 = complex
+/// with spatial differencing, 40 = JPEG2000, 41 = PNG) and the bits per packed value
+/// Read at open, so it is available for messages whose values fail to decode
+/// Returns false for unknown messages; bits is 255 for templates without a known width
+#[no_mangle]
+pub extern "C" fn grib2_get_packing_info(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+    out_template_number: *mut u16,
+    out_bits_per_value: *mut u8,
+) -> bool {
+    let Some((template, bits)) = with_reader(reader, None, |reader| {
+        reader
+            .messages
+            .get(message_index)
+            .map(|m| (m.packing_template, m.bits_per_value))
+    }) else {
+        return false;
+    };
+    unsafe {
+        if !out_template_number.is_null() {
+            *out_template_number = template;
+        }
+        if !out_bits_per_value.is_null() {
+            *out_bits_per_value = bits;
+        }
+    }
+    true
+}
+
+/// Get the packing parameters of section 5: values unpack as (R + X * 2^E) / 10^D
+/// from X stored in bits_per_value bits, so they bound the precision of a field
+/// (e.g. D = 1 rounds temperatures to 0.1 K). Read at open like grib2_get_packing_info
+/// Returns false for unknown messages and templates without R/E/D (e.g. 5.200)
+#[no_mangle]
+pub extern "C" fn grib2_get_decode_params(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+    out_reference: *mut f32,
+    out_binary_scale: *mut i16,
+    out_decimal_scale: *mut i16,
+    out_bits: *mut u8,
+) -> bool {
+    let Some(((reference, binary_scale, decimal_scale), bits)) =
+        with_reader(reader, None, |reader| {
+            let msg = reader.messages.get(message_index)?;
+            Some((msg.packing_scale?, msg.bits_per_value))
+        })
+    else {
+        return false;
+    };
+    unsafe {
+        if !out_reference.is_null() {
+            *out_reference = reference;
+        }
+        if !out_binary_scale.is_null() {
+            *out_binary_scale = binary_scale;
+        }
+        if !out_decimal_scale.is_null() {
+            *out_decimal_scale = decimal_scale;
+        }
+        if !out_bits.is_null() {
+            *out_bits = bits;
+        }
+    }
+    true
+}
+
+/// Significant bits each value of a message keeps: the packed width for templates
+/// scaling integers (so 0 for a constant field), 24 or 53 for IEEE single or double
+/// floats (5.4); 255 for unknown messages and other templates. Read at open
+#[no_mangle]
+pub extern "C" fn grib2_get_precision_bits(reader: *mut Grib2Reader, message_index: usize) -> u8 {
+    with_reader(reader, 255, |reader| {
+        reader
+            .messages
+            .get(message_index)
+            .map(|m| m.precision_bits)
+            .unwrap_or(255)
+    })
+}
+
+/// Whether section 5 packs a message with 0 bits per value, so every point that
+/// isn't bitmapped out holds the reference value (R / 10^D); read at open
+/// Returns false for unknown messages
+#[no_mangle]
+pub extern "C" fn grib2_has_constant_field(reader: *mut Grib2Reader, message_index: usize) -> bool {
+    with_reader(reader, false, |reader| {
+        reader
+            .messages
+            .get(message_index)
+            .is_some_and(constant_field)
+    })
+}
+
+/// Get the extent of a message from the first and last grid points of section 3,
+/// without decoding it. Longitudes follow grib2_set_lon_convention; min_lon > max_lon
+/// means the grid crosses the antimeridian (the 0° meridian under the Unsigned
+/// convention), as for grib2_open_with_bbox. Global grids span the whole range
+/// Returns false for unknown messages and for rotated or projected grids, whose
+/// corner points don't bound them
+#[no_mangle]
+pub extern "C" fn grib2_get_message_bounds(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+    out_min_lat: *mut f64,
+    out_min_lon: *mut f64,
+    out_max_lat: *mut f64,
+    out_max_lon: *mut f64,
+) -> bool {
+    let Some((min_lat, min_lon, max_lat, max_lon)) = with_reader(reader, None, |reader| {
+        let (min_lat, west, max_lat, east) = reader.messages.get(message_index)?.bounds?;
+        let convention = reader.filter.lon_convention;
+        let (min_lon, max_lon) = match convention {
+            // Already signed, and an east edge of 180 must not wrap to -180
+            Grib2LonConvention::Signed => (west, east),
+            Grib2LonConvention::Unsigned if (west, east) == (-180.0, 180.0) => (0.0, 360.0),
+            Grib2LonConvention::Unsigned => (
+                normalize_longitude(west, convention),
+                normalize_longitude(east, convention),
+            ),
+        };
+        Some((min_lat, min_lon, max_lat, max_lon))
+    }) else {
+        return false;
+    };
+    unsafe {
+        if !out_min_lat.is_null() {
+            *out_min_lat = min_lat;
+        }
+        if !out_min_lon.is_null() {
+            *out_min_lon = min_lon;
+        }
+        if !out_max_lat.is_null() {
+            *out_max_lat = max_lat;
+        }
+        if !out_max_lon.is_null() {
+            *out_max_lon = max_lon;
+        }
+    }
+    true
+}
+
+/// Get the native grid shape of a message
+/// Ni counts points along a parallel, Nj along a meridian, scan_mode is flag table 3.4
+/// Returns false for unknown messages or unsupported grid templates
+#[no_mangle]
+pub extern "C" fn grib2_get_grid_info(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+    out_ni: *mut u32,
+    out_nj: *mut u32,
+    out_scan_mode: *mut u8,
+) -> bool {
+    let grid = match with_reader(reader, None, |reader| {
+        reader.messages.get(message_index).map(|m| m.grid)
+    }) {
+        Some(grid) if grid.is_known() => grid,
+        _ => return false,
+    };
+    unsafe {
+        if !out_ni.is_null() {
+            *out_ni = grid.ni;
+        }
+        if !out_nj.is_null() {
+            *out_nj = grid.nj;
+        }
+        if !out_scan_mode.is_null() {
+            *out_scan_mode = grid.scan_mode;
+        }
+    }
+    true
+}
+
+/// Get the grid definition template of a message (code table 3.1, e.g. 0 = regular
+/// lat/lon, 1 = rotated lat/lon, 30 = Lambert conformal, 40 = Gaussian)
+/// Files can mix grids, so this, like every grid getter, describes one message only
+/// Returns 65535 (missing) for unknown messages
+#[no_mangle]
+pub extern "C" fn grib2_get_grid_template(reader: *mut Grib2Reader, message_index: usize) -> u16 {
+    with_reader(reader, u16::MAX, |reader| {
+        reader
+            .messages
+            .get(message_index)
+            .map_or(u16::MAX, |m| m.grid.template)
+    })
+}
+
+/// Check whether all messages share one grid definition (identical section 3), so
+/// coordinates computed for one message hold for all of them. True with fewer than
+/// two messages; derived wind messages sit on the grid of their components
+/// Returns false for a null reader
+#[no_mangle]
+pub extern "C" fn grib2_file_has_uniform_grid(reader: *mut Grib2Reader) -> bool {
+    with_reader(reader, false, |reader| reader.has_uniform_grid())
+}
+
+/// Get where the GRIB message holding a field sits in its source: the byte offset
+/// of its "GRIB" indicator and its total length from section 0, e.g. to build an
+/// .idx-style index for range requests. Offsets count from the start of the data
+/// as read, so they are in the decompressed stream of .gz/.bz2 files and in the
+/// fetched range of a ranged URL. Fields of one message share its extent
+/// Returns false for unknown messages and for derived wind messages
+#[no_mangle]
+pub extern "C" fn grib2_get_message_extent(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+    out_offset: *mut u64,
+    out_length: *mut u64,
+) -> bool {
+    let Some((offset, length)) = with_reader(reader, None, |reader| {
+        let msg = reader.messages.get(message_index)?;
+        msg.derived.is_none().then_some(msg.extent)
+    }) else {
+        return false;
+    };
+    unsafe {
+        if !out_offset.is_null() {
+            *out_offset = offset;
+        }
+        if !out_length.is_null() {
+            *out_length = length;
+        }
+    }
+    true
+}
 
-            let points: Vec<(f64, f64, f64)> = latlons
-                .zip(values)
-                .map(|((lat, lon), value)| {
-                    let lon_normalized = if lon > 180.0 { lon - 360.0 } else { lon };
-                    (lat as f64, lon_normalized as f64, value as f64)
-                })
-                .collect();
+/// Get the scanning-mode flags of a message (flag table 3.4) that fix how grid
+/// cells map to the stream and grib2_get_value_at: i_negative for points running
+/// west along a row, j_positive for rows running north, j_consecutive when
+/// points follow a column instead of a row, alternating when every other row
+/// (or column) runs the opposite way. False for unknown messages or unsupported grids
+#[no_mangle]
+pub extern "C" fn grib2_get_scan_flags(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+    out_i_negative: *mut bool,
+    out_j_positive: *mut bool,
+    out_j_consecutive: *mut bool,
+    out_alternating: *mut bool,
+) -> bool {
+    let grid = match with_reader(reader, None, |reader| {
+        reader.messages.get(message_index).map(|m| m.grid)
+    }) {
+        Some(grid) if grid.is_known() => grid,
+        _ => return false,
+    };
+    let flags = [
+        (out_i_negative, 0x80),
+        (out_j_positive, 0x40),
+        (out_j_consecutive, 0x20),
+        (out_alternating, 0x10),
+    ];
+    for (out, bit) in flags {
+        if !out.is_null() {
+            unsafe { *out = grid.scan_mode & bit != 0 };
+        }
+    }
+    true
+}
 
-            messages.push(ParsedMessage {
-                discipline,
-                parameter_category: param_cat,
-                parameter_number: param_num,
-                forecast_time,
-                surface_type,
-                surface_value,
-                message_index: flat_index,
-                points,
-            });
+/// Get the value of grid cell (i, j) of one message, i along a parallel (0..Ni) and
+/// j along a meridian (0..Nj), both counted from the first grid point in the
+/// scanning directions of grib2_get_grid_info's scan_mode. Masked points yield NaN;
+/// OutsideGrid for indices past Ni/Nj, UnsupportedGrid for grids without a
+/// rectangular layout (e.g. reduced Gaussian)
+#[no_mangle]
+pub extern "C" fn grib2_get_value_at(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+    i: u32,
+    j: u32,
+    out_value: *mut c_double,
+) -> Grib2QueryStatus {
+    if out_value.is_null() {
+        return Grib2QueryStatus::InvalidMessage;
+    }
+    let result = with_reader(reader, Err(Grib2QueryStatus::InvalidMessage), |reader| {
+        reader.value_at(message_index, i as usize, j as usize)
+    });
+    match result {
+        Ok(value) => {
+            unsafe { *out_value = value; }
+            Grib2QueryStatus::Ok
         }
+        Err(status) => status,
+    }
+}
 
-        Ok(Grib2Reader {
-            messages,
-            current_message: 0,
-            current_point: 0,
-        })
+/// Look up the abbreviation ("TMP") and name ("Temperature") of a parameter
+/// Both strings are static and must not be freed; unmapped triples get "unknown"
+/// Local entries are taken as NCEP's; grib2_get_message_parameter checks the centre
+/// Returns true if the triple is in the table
+#[no_mangle]
+pub extern "C" fn grib2_parameter_name(
+    discipline: u8,
+    category: u8,
+    number: u8,
+    out_abbrev: *mut *const c_char,
+    out_name: *mut *const c_char,
+) -> bool {
+    let param = parameters::lookup(discipline, category, number);
+    let (abbrev, name) = match param {
+        Some(p) => (p.abbrev, p.name),
+        None => (parameters::UNKNOWN, parameters::UNKNOWN),
+    };
+    unsafe {
+        if !out_abbrev.is_null() {
+            *out_abbrev = abbrev.as_ptr();
+        }
+        if !out_name.is_null() {
+            *out_name = name.as_ptr();
+        }
     }
+    param.is_some()
+}
 
-    /// Open from file path
-    fn new(path: &str) -> Result<Self, String> {
-        let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
-        let reader = BufReader::new(file);
-        Self::from_reader(reader)
+/// Look up the canonical units of a parameter ("K", "m s-1", "kg m-2")
+/// The string is static and must not be freed; unmapped triples get ""
+/// Returns true if the triple is in the table
+#[no_mangle]
+pub extern "C" fn grib2_parameter_units(
+    discipline: u8,
+    category: u8,
+    number: u8,
+    out_units: *mut *const c_char,
+) -> bool {
+    let param = parameters::lookup(discipline, category, number);
+    let units = param.map(|p| p.units).unwrap_or(c"");
+    unsafe {
+        if !out_units.is_null() {
+            *out_units = units.as_ptr();
+        }
     }
+    param.is_some()
+}
 
-    /// Open from in-memory bytes (copies data to owned Vec for Seek support)
-    fn from_bytes(data: &[u8]) -> Result<Self, String> {
-        let owned_data = data.to_vec();
-        let cursor = Cursor::new(owned_data);
-        Self::from_reader(cursor)
+/// Look up the abbreviation, name and units of a message's parameter as its section 1
+/// defines it: local entries (192-254) resolve only for NCEP messages declaring local
+/// tables, so other centres' local parameters come back as "unknown" rather than with
+/// NCEP's meaning. Static strings; "unknown"/"" when unresolved
+/// Returns false for unknown messages and unresolved parameters
+#[no_mangle]
+pub extern "C" fn grib2_get_message_parameter(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+    out_abbrev: *mut *const c_char,
+    out_name: *mut *const c_char,
+    out_units: *mut *const c_char,
+) -> bool {
+    let Some(param) = with_reader(reader, None, |reader| {
+        reader
+            .messages
+            .get(message_index)
+            .map(ParsedMessage::parameter)
+    }) else {
+        return false;
+    };
+    let (abbrev, name, units) = match param {
+        Some(p) => (p.abbrev, p.name, p.units),
+        None => (parameters::UNKNOWN, parameters::UNKNOWN, c""),
+    };
+    unsafe {
+        if !out_abbrev.is_null() {
+            *out_abbrev = abbrev.as_ptr();
+        }
+        if !out_name.is_null() {
+            *out_name = name.as_ptr();
+        }
+        if !out_units.is_null() {
+            *out_units = units.as_ptr();
+        }
     }
+    param.is_some()
+}
 
-    fn read_batch(&mut self, max_count: usize) -> Grib2Batch {
-        let mut points = Vec::with_capacity(max_count);
+/// Whether values of a parameter are code-table entries (precipitation type,
+/// categorical rain, ...) rather than physical quantities; false for unmapped triples
+#[no_mangle]
+pub extern "C" fn grib2_parameter_is_categorical(discipline: u8, category: u8, number: u8) -> bool {
+    parameters::lookup(discipline, category, number).is_some_and(parameters::is_categorical)
+}
 
-        while points.len() < max_count {
-            if self.current_message >= self.messages.len() {
-                break;
-            }
+/// How a parameter's values should be typed: GRIB2_VALUE_CONTINUOUS for quantities
+/// and GRIB2_VALUE_CATEGORICAL for code-table entries; unmapped triples are taken
+/// as continuous
+#[no_mangle]
+pub extern "C" fn grib2_parameter_value_kind(discipline: u8, category: u8, number: u8) -> u8 {
+    parameters::lookup(discipline, category, number)
+        .map_or(parameters::VALUE_CONTINUOUS, parameters::value_kind)
+}
 
-            let msg = &self.messages[self.current_message];
-
-            while self.current_point < msg.points.len() && points.len() < max_count {
-                let (lat, lon, value) = msg.points[self.current_point];
-                points.push(Grib2DataPoint {
-                    latitude: lat,
-                    longitude: lon,
-                    value,
-                    discipline: msg.discipline,
-                    parameter_category: msg.parameter_category,
-                    parameter_number: msg.parameter_number,
-                    forecast_time: msg.forecast_time,
-                    surface_type: msg.surface_type,
-                    surface_value: msg.surface_value,
-                    message_index: msg.message_index,
-                });
-                self.current_point += 1;
-            }
+/// Pack a parameter triple into one stable key for hashing and joins:
+/// discipline << 16 | category << 8 | number
+#[no_mangle]
+pub extern "C" fn grib2_parameter_key(discipline: u8, category: u8, number: u8) -> u32 {
+    parameters::key(discipline, category, number)
+}
 
-            if self.current_point >= msg.points.len() {
-                self.current_message += 1;
-                self.current_point = 0;
-            }
+/// Split a key from grib2_parameter_key back into its triple
+/// Returns false, leaving the outputs untouched, when the top byte is set
+#[no_mangle]
+pub extern "C" fn grib2_parameter_unpack(
+    key: u32,
+    out_discipline: *mut u8,
+    out_category: *mut u8,
+    out_number: *mut u8,
+) -> bool {
+    let Some((discipline, category, number)) = parameters::unpack_key(key) else {
+        return false;
+    };
+    unsafe {
+        if !out_discipline.is_null() {
+            *out_discipline = discipline;
         }
-
-        let has_more = self.current_message < self.messages.len();
-        let count = points.len();
-
-        if count == 0 {
-            Grib2Batch {
-                data: ptr::null_mut(),
-                count: 0,
-                has_more: false,
-                error: ptr::null_mut(),
-            }
-        } else {
-            let data = points.as_mut_ptr();
-            std::mem::forget(points);
-            Grib2Batch {
-                data,
-                count,
-                has_more,
-                error: ptr::null_mut(),
-            }
+        if !out_category.is_null() {
+            *out_category = category;
+        }
+        if !out_number.is_null() {
+            *out_number = number;
         }
     }
+    true
+}
 
-    fn total_points(&self) -> usize {
-        self.messages.iter().map(|m| m.points.len()).sum()
+/// Get the value of the grid point nearest to (lat, lon) in one message
+/// Masked (bitmap) points yield NaN; coordinates off a regional grid return OutsideGrid
+#[no_mangle]
+pub extern "C" fn grib2_point_query(
+    reader: *mut Grib2Reader,
+    lat: c_double,
+    lon: c_double,
+    message_index: usize,
+    out_value: *mut c_double,
+) -> Grib2QueryStatus {
+    if out_value.is_null() {
+        return Grib2QueryStatus::InvalidMessage;
+    }
+    let result = with_reader(reader, Err(Grib2QueryStatus::InvalidMessage), |reader| {
+        reader.point_query(message_index, lat, lon)
+    });
+    match result {
+        Ok(value) => {
+            unsafe { *out_value = value; }
+            Grib2QueryStatus::Ok
+        }
+        Err(status) => status,
     }
 }
 
-// ============ C FFI Functions ============
-
-/// Open a GRIB2 file for streaming reads
-/// Returns opaque handle, caller must close with grib2_close
+/// Bilinearly interpolate a message at (lat, lon); regular lat/lon grids only
+/// If any of the four corners is masked the nearest point is used and NearestFallback returned
 #[no_mangle]
-pub extern "C" fn grib2_open(path: *const c_char) -> *mut Grib2Reader {
-    let path_str = match unsafe { CStr::from_ptr(path) }.to_str() {
-        Ok(s) => s,
-        Err(_) => return ptr::null_mut(),
-    };
-
-    match Grib2Reader::new(path_str) {
-        Ok(reader) => Box::into_raw(Box::new(reader)),
-        Err(_) => ptr::null_mut(),
+pub extern "C" fn grib2_interpolate(
+    reader: *mut Grib2Reader,
+    lat: c_double,
+    lon: c_double,
+    message_index: usize,
+    out_value: *mut c_double,
+) -> Grib2QueryStatus {
+    if out_value.is_null() {
+        return Grib2QueryStatus::InvalidMessage;
+    }
+    let result = with_reader(reader, Err(Grib2QueryStatus::InvalidMessage), |reader| {
+        reader.interpolate(message_index, lat, lon)
+    });
+    match result {
+        Ok((value, status)) => {
+            unsafe { *out_value = value; }
+            status
+        }
+        Err(status) => status,
     }
 }
 
-/// Open a GRIB2 file and get error message if failed
+/// Resample a message onto a regular lat/lon grid with the given steps in degrees
+/// The grid covers the message's extent, nodes on multiples of the steps, rows from
+/// north to south; nodes without a source point nearby are NaN. Works for any grid
+/// the reader can locate (Gaussian, Lambert, polar stereographic, rotated, ...)
+/// out_batch always receives a batch to free with grib2_free_batch, carrying the
+/// error on failure; out_ni/out_nj get the column and row counts
 #[no_mangle]
-pub extern "C" fn grib2_open_with_error(path: *const c_char, error: *mut *mut c_char) -> *mut Grib2Reader {
-    let path_str = match unsafe { CStr::from_ptr(path) }.to_str() {
-        Ok(s) => s,
-        Err(e) => {
+pub extern "C" fn grib2_regrid(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+    target_lat_step: c_double,
+    target_lon_step: c_double,
+    method: Grib2RegridMethod,
+    out_batch: *mut Grib2Batch,
+    out_ni: *mut u32,
+    out_nj: *mut u32,
+) -> bool {
+    if out_batch.is_null() {
+        return false;
+    }
+    let result = with_reader(reader, Err("Null reader".to_string()), |reader| {
+        reader.regrid_message(message_index, target_lat_step, target_lon_step, method)
+    });
+    let (batch, ni, nj) = match result {
+        Ok(regridded) => regridded,
+        Err(message) => {
             unsafe {
-                *error = CString::new(format!("Invalid UTF-8 in path: {}", e))
-                    .unwrap()
-                    .into_raw();
+                *out_batch = Grib2Batch::failed(message);
             }
-            return ptr::null_mut();
+            return false;
         }
     };
-
-    match Grib2Reader::new(path_str) {
-        Ok(reader) => {
-            unsafe { *error = ptr::null_mut(); }
-            Box::into_raw(Box::new(reader))
+    unsafe {
+        *out_batch = batch;
+        if !out_ni.is_null() {
+            *out_ni = ni as u32;
         }
-        Err(e) => {
-            unsafe {
-                *error = CString::new(e).unwrap().into_raw();
-            }
-            ptr::null_mut()
+        if !out_nj.is_null() {
+            *out_nj = nj as u32;
         }
     }
+    true
 }
 
-/// Open a GRIB2 reader from in-memory bytes (for HTTP fetched data)
-/// Returns opaque handle, caller must close with grib2_close
+/// Export one message as an Arrow record batch through the C Data Interface
+/// Columns: latitude, longitude, value and the message metadata repeated per row
+/// On success out_array/out_schema are filled and the caller must call their
+/// release callbacks; on failure they are left untouched and error (if not null)
+/// receives a message to free with grib2_free_error
+#[cfg(feature = "arrow")]
 #[no_mangle]
-pub extern "C" fn grib2_open_from_bytes(
-    data: *const u8,
-    len: usize,
-    error: *mut *mut c_char
-) -> *mut Grib2Reader {
-    if data.is_null() || len == 0 {
-        unsafe {
-            *error = CString::new("Empty or null data").unwrap().into_raw();
+pub extern "C" fn grib2_to_arrow(
+    reader: *mut Grib2Reader,
+    message_index: usize,
+    out_array: *mut arrow_data::ffi::FFI_ArrowArray,
+    out_schema: *mut arrow_schema::ffi::FFI_ArrowSchema,
+    error: *mut *mut c_char,
+) -> bool {
+    let result = if out_array.is_null() || out_schema.is_null() {
+        Err("Null output pointer".to_string())
+    } else {
+        with_reader(reader, Err("Null reader".to_string()), |reader| {
+            reader.export_arrow(message_index)
+        })
+    };
+    match result {
+        Ok((array, schema)) => {
+            unsafe {
+                ptr::write(out_array, array);
+                ptr::write(out_schema, schema);
+            }
+            true
+        }
+        Err(message) => {
+            if !error.is_null() {
+                unsafe {
+                    *error = error_string(message);
+                }
+            }
+            false
         }
-        return ptr::null_mut();
     }
+}
 
-    let bytes = unsafe { std::slice::from_raw_parts(data, len) };
+/// Encode points of a regular lat/lon grid as one GRIB2 message (grid template 3.0,
+/// product template 4.0, simple packing with a bitmap for NaN values)
+/// Points run row by row, ni per row, in the order grib2_regrid returns them; the
+/// reference time, forecast time (hours) and levels come from the first point
+/// On success out_bytes/out_len receive the message, freed with grib2_free_encoded
+#[no_mangle]
+pub extern "C" fn grib2_encode_regular_latlon(
+    points: *const Grib2DataPoint,
+    count: usize,
+    ni: u32,
+    nj: u32,
+    discipline: u8,
+    category: u8,
+    number: u8,
+    out_bytes: *mut *mut u8,
+    out_len: *mut usize,
+) -> bool {
+    if points.is_null() || out_bytes.is_null() || out_len.is_null() {
+        return false;
+    }
+    let points = unsafe { std::slice::from_raw_parts(points, count) };
+    let encoded = catch_panic(|| {
+        encode::regular_latlon(
+            points,
+            ni as usize,
+            nj as usize,
+            (discipline, category, number),
+        )
+    });
+    let Ok(Ok(message)) = encoded else {
+        return false;
+    };
+    let (data, len) = leak_vec(message);
+    unsafe {
+        *out_bytes = data;
+        *out_len = len;
+    }
+    true
+}
 
-    match Grib2Reader::from_bytes(bytes) {
-        Ok(reader) => {
-            unsafe { *error = ptr::null_mut(); }
-            Box::into_raw(Box::new(reader))
-        }
-        Err(e) => {
-            unsafe {
-                *error = CString::new(e).unwrap().into_raw();
-            }
-            ptr::null_mut()
+/// Free a message returned by grib2_encode_regular_latlon
+#[no_mangle]
+pub extern "C" fn grib2_free_encoded(bytes: *mut u8, len: usize) {
+    if !bytes.is_null() {
+        unsafe {
+            drop(Vec::from_raw_parts(bytes, len, len));
         }
     }
 }
 
-/// Read a batch of data points (up to max_count)
-/// Caller must free batch with grib2_free_batch
+/// Close the reader and free resources
 #[no_mangle]
-pub extern "C" fn grib2_read_batch(reader: *mut Grib2Reader, max_count: usize) -> Grib2Batch {
+pub extern "C" fn grib2_close(reader: *mut Grib2Reader) {
     if reader.is_null() {
-        return Grib2Batch {
-            data: ptr::null_mut(),
-            count: 0,
-            has_more: false,
-            error: CString::new("Null reader").unwrap().into_raw(),
-        };
+        return;
+    }
+    let reader = unsafe { Box::from_raw(reader) };
+    // Batches stay valid after close, but ones never freed are a caller bug worth flagging;
+    // dropping their entries keeps the map bounded and leaves real leaks visible to LSan
+    let unfreed = {
+        let mut batches = outstanding_batches();
+        let before = batches.len();
+        batches.retain(|_, id| *id != reader.id);
+        before - batches.len()
+    };
+    if unfreed > 0 {
+        log_event!(
+            Warn,
+            "grib2_close: {unfreed} batch(es) from this reader were never freed"
+        );
     }
+}
 
-    let reader = unsafe { &mut *reader };
-    reader.read_batch(max_count)
+/// Number of batches returned by this reader that have not been passed to a free
+/// function yet; lets callers check that a scan released everything it read
+#[no_mangle]
+pub extern "C" fn grib2_outstanding_batches(reader: *mut Grib2Reader) -> usize {
+    with_reader(reader, 0, |reader| outstanding_count(reader.id))
 }
 
-/// Get total number of data points in file (for cardinality)
+/// Free a batch of data points
 #[no_mangle]
-pub extern "C" fn grib2_total_points(reader: *mut Grib2Reader) -> usize {
-    if reader.is_null() {
-        return 0;
+pub extern "C" fn grib2_free_batch(batch: Grib2Batch) {
+    free_batch_data(batch.data, batch.count);
+    if !batch.error.is_null() {
+        unsafe {
+            let _ = CString::from_raw(batch.error);
+        }
     }
-    let reader = unsafe { &*reader };
-    reader.total_points()
 }
 
-/// Close the reader and free resources
+/// Free a batch of single-precision data points
 #[no_mangle]
-pub extern "C" fn grib2_close(reader: *mut Grib2Reader) {
-    if !reader.is_null() {
+pub extern "C" fn grib2_free_batch_f32(batch: Grib2BatchF32) {
+    free_batch_data(batch.data, batch.count);
+    if !batch.error.is_null() {
         unsafe {
-            let _ = Box::from_raw(reader);
+            let _ = CString::from_raw(batch.error);
         }
     }
 }
 
-/// Free a batch of data points
+/// Free a batch of data points with packed integers
 #[no_mangle]
-pub extern "C" fn grib2_free_batch(batch: Grib2Batch) {
-    if !batch.data.is_null() {
+pub extern "C" fn grib2_free_batch_raw(batch: Grib2BatchRaw) {
+    free_batch_data(batch.data, batch.count);
+    if !batch.error.is_null() {
         unsafe {
-            let _ = Vec::from_raw_parts(batch.data, batch.count, batch.count);
+            let _ = CString::from_raw(batch.error);
         }
     }
+}
+
+/// Free a batch of data points with H3 cells
+#[cfg(feature = "h3")]
+#[no_mangle]
+pub extern "C" fn grib2_free_batch_h3(batch: Grib2BatchH3) {
+    free_batch_data(batch.data, batch.count);
     if !batch.error.is_null() {
         unsafe {
             let _ = CString::from_raw(batch.error);
@@ -344,33 +6050,79 @@ pub struct Grib2ReadResult {
 /// Read entire GRIB2 file at once (legacy)
 #[no_mangle]
 pub extern "C" fn grib2_read_file(path: *const c_char) -> Grib2ReadResult {
-    let path_str = match unsafe { CStr::from_ptr(path) }.to_str() {
-        Ok(s) => s,
-        Err(_) => {
-            return Grib2ReadResult {
+    let mut error = ptr::null_mut();
+    let reader = open_path(path, &mut error, ptr::null_mut(), |path| {
+        Grib2Reader::open(path)
+    });
+    if reader.is_null() {
+        return Grib2ReadResult {
+            data: ptr::null_mut(),
+            count: 0,
+            error,
+        };
+    }
+
+    read_and_close(reader)
+}
+
+/// Read every point of an open reader into a legacy result and close the reader
+/// The points are handed over to the result, so they no longer count as a batch
+fn read_and_close(reader: *mut Grib2Reader) -> Grib2ReadResult {
+    let batch = grib2_read_batch(reader, grib2_total_points(reader));
+    if !batch.data.is_null() {
+        outstanding_batches().remove(&(batch.data as usize));
+    }
+    grib2_close(reader);
+    Grib2ReadResult {
+        data: batch.data,
+        count: batch.count,
+        error: batch.error,
+    }
+}
+
+/// Read every point of one parameter across all levels and times without keeping
+/// a reader: opens with the filter of grib2_open_with_filter, reads and closes
+/// Pass -1 for discipline, category or number to match any value. On failure
+/// out_result is left empty and *error (freed with grib2_free_error) says why;
+/// free out_result with grib2_free_result either way
+#[no_mangle]
+pub extern "C" fn grib2_read_filtered(
+    path: *const c_char,
+    discipline: c_int,
+    category: c_int,
+    number: c_int,
+    out_result: *mut Grib2ReadResult,
+    error: *mut *mut c_char,
+) -> bool {
+    if out_result.is_null() {
+        let e = Grib2Error::new(Grib2ErrorCode::InvalidArgument, "out_result is null");
+        report_error(Some(e), error, ptr::null_mut());
+        return false;
+    }
+    let reader = grib2_open_with_filter(path, discipline, category, number, error, ptr::null_mut());
+    if reader.is_null() {
+        unsafe {
+            *out_result = Grib2ReadResult {
                 data: ptr::null_mut(),
                 count: 0,
-                error: CString::new("Invalid UTF-8 in path").unwrap().into_raw(),
-            };
-        }
-    };
-
-    match Grib2Reader::new(path_str) {
-        Ok(mut reader) => {
-            let total = reader.total_points();
-            let batch = reader.read_batch(total);
-            Grib2ReadResult {
-                data: batch.data,
-                count: batch.count,
-                error: batch.error,
+                error: ptr::null_mut(),
             }
-        }
-        Err(e) => Grib2ReadResult {
-            data: ptr::null_mut(),
-            count: 0,
-            error: CString::new(e).unwrap().into_raw(),
-        },
+        };
+        return false;
+    }
+    let mut result = read_and_close(reader);
+    // Errors go to *error only, so the result never carries one
+    let batch_error = std::mem::replace(&mut result.error, ptr::null_mut());
+    unsafe { *out_result = result };
+    if batch_error.is_null() {
+        return true;
     }
+    if error.is_null() {
+        grib2_free_error(batch_error);
+    } else {
+        unsafe { *error = batch_error };
+    }
+    false
 }
 
 /// Free legacy result
@@ -387,3 +6139,360 @@ pub extern "C" fn grib2_free_result(result: Grib2ReadResult) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn surface_value_scales_isobaric_levels_to_pa() {
+        assert_eq!(surface_value(&FixedSurface::new(100, 0, 85000)), 85000.0);
+        assert_eq!(surface_value(&FixedSurface::new(100, -2, 850)), 85000.0);
+        // Missing scale factor: the scaled value is taken as is
+        assert_eq!(
+            surface_value(&FixedSurface::new(100, i8::MIN + 1, 50000)),
+            50000.0
+        );
+    }
+
+    fn open_fixture(bytes: &[u8]) -> *mut Grib2Reader {
+        let reader = grib2_open_from_bytes(
+            bytes.as_ptr(),
+            bytes.len(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        );
+        assert!(!reader.is_null());
+        reader
+    }
+
+    /// The registry only counts batches; `make rust-leak-check` runs this under
+    /// LeakSanitizer, which fails if a free path keeps the allocation
+    #[test]
+    fn freed_batches_are_not_outstanding() {
+        let reader = open_fixture(include_bytes!("../../test/data/complex_packing.grib2"));
+        let batch = grib2_read_batch(reader, 4);
+        assert!(batch.error.is_null() && batch.count == 4);
+        assert_eq!(grib2_outstanding_batches(reader), 1);
+        grib2_free_batch(batch);
+        assert_eq!(grib2_outstanding_batches(reader), 0);
+
+        // A batch left unfreed is counted until the reader closes
+        let unfreed = grib2_read_batch(reader, 4);
+        assert_eq!(grib2_outstanding_batches(reader), 1);
+        grib2_close(reader);
+        grib2_free_batch(unfreed);
+    }
+
+    /// One GRIB2 message repeating the field (sections 4 to 7) of `message` `fields` times
+    fn repeat_fields(message: &[u8], fields: usize) -> Vec<u8> {
+        let mut pos = 16;
+        let mut first_field = None;
+        while &message[pos..pos + 4] != b"7777" {
+            if message[pos + 4] == 4 {
+                first_field.get_or_insert(pos);
+            }
+            pos += u32::from_be_bytes(message[pos..pos + 4].try_into().unwrap()) as usize;
+        }
+        let first_field = first_field.unwrap();
+        let mut out = message[..first_field].to_vec();
+        for _ in 0..fields {
+            out.extend(&message[first_field..pos]);
+        }
+        out.extend(b"7777");
+        let len = out.len() as u64;
+        out[8..16].copy_from_slice(&len.to_be_bytes());
+        out
+    }
+
+    #[test]
+    fn message_and_submessage_indices_do_not_collide() {
+        // Field 1000 of message 0 and field 0 of message 1 shared index 1000 when
+        // both were packed as message * 1000 + field
+        let file = include_bytes!("../../test/data/latlon_constant_field.grib2");
+        let first = &file[..u64::from_be_bytes(file[8..16].try_into().unwrap()) as usize];
+        let mut bytes = repeat_fields(first, 1001);
+        bytes.extend_from_slice(first);
+
+        let reader = open_fixture(&bytes);
+        let mut fields = HashSet::new();
+        loop {
+            let batch = grib2_read_batch(reader, 4096);
+            assert!(batch.error.is_null());
+            if batch.count == 0 {
+                grib2_free_batch(batch);
+                break;
+            }
+            let points = unsafe { std::slice::from_raw_parts(batch.data, batch.count) };
+            fields.extend(points.iter().map(|p| (p.message_index, p.submessage_index)));
+            grib2_free_batch(batch);
+        }
+        grib2_close(reader);
+        assert_eq!(fields.len(), 1002);
+        assert!(fields.contains(&(0, 1000)) && fields.contains(&(1, 0)));
+    }
+
+    fn take_error(error: *mut c_char) -> String {
+        assert!(!error.is_null());
+        let message = unsafe { CStr::from_ptr(error) }
+            .to_string_lossy()
+            .into_owned();
+        grib2_free_error(error);
+        message
+    }
+
+    #[test]
+    fn error_string_strips_interior_nul_bytes() {
+        assert_eq!(take_error(error_string("a\0b")), "ab");
+    }
+
+    #[test]
+    fn malformed_bytes_give_a_null_handle_and_an_error() {
+        let file = include_bytes!("../../test/data/complex_packing.grib2");
+        let mut garbage = b"GRIB\0\0\0\x02".to_vec();
+        garbage.extend((0..200u32).map(|k| (k * 37 % 251) as u8));
+        for bytes in [&file[..file.len() / 2], &garbage[..], b"not a grib file"] {
+            let mut error = ptr::null_mut();
+            let mut code = Grib2ErrorCode::Ok;
+            let reader = grib2_open_from_bytes(bytes.as_ptr(), bytes.len(), &mut error, &mut code);
+            assert!(reader.is_null());
+            assert_ne!(code, Grib2ErrorCode::Ok);
+            assert!(!take_error(error).is_empty());
+        }
+    }
+
+    #[cfg(feature = "h3")]
+    #[test]
+    fn h3_cells_match_published_mappings() {
+        let cell = |lat, lon, res| h3_cell(lat, lon, h3o::Resolution::try_from(res).unwrap());
+        // Examples from the H3 documentation and the h3-js and h3-py READMEs
+        assert_eq!(cell(40.689167, -74.044444, 10), 0x8a2a1072b59ffff);
+        assert_eq!(cell(37.3615593, -122.0553238, 7), 0x87283472bffffff);
+        assert_eq!(cell(37.769377, -122.388903, 9), 0x89283082e73ffff);
+        assert_eq!(cell(f64::NAN, 0.0, 9), 0);
+
+        let reader = open_fixture(include_bytes!("../../test/data/mixed_grids.grib2"));
+        let batch = grib2_read_batch_h3(reader, 64, 9);
+        assert!(batch.error.is_null() && batch.count > 0);
+        let points = unsafe { std::slice::from_raw_parts(batch.data, batch.count) };
+        for p in points {
+            assert_eq!(p.h3_cell, cell(p.point.latitude, p.point.longitude, 9));
+        }
+        grib2_free_batch_h3(batch);
+        grib2_close(reader);
+    }
+
+    #[test]
+    fn normalize_longitude_signed() {
+        let signed = |lon| normalize_longitude(lon, Grib2LonConvention::Signed);
+        // 0..360 input
+        for (lon, expected) in [(0.0, 0.0), (90.0, 90.0), (190.0, -170.0), (359.5, -0.5)] {
+            assert_eq!(signed(lon), expected);
+        }
+        // -180..180 input is unchanged, except the antimeridian
+        for lon in [-179.5, -90.0, -0.5, 45.0, 179.5] {
+            assert_eq!(signed(lon), lon);
+        }
+        assert_eq!(signed(180.0), -180.0);
+        assert_eq!(signed(-180.0), -180.0);
+        assert_eq!(signed(360.0), 0.0);
+        assert_eq!(signed(540.0), -180.0);
+        assert_eq!(signed(-190.0), 170.0);
+        // Just west of -180: the sum wraps to a remainder that rounds up to 360
+        assert_eq!(signed(-180.0 - 3e-14), -180.0);
+    }
+
+    #[test]
+    fn normalize_longitude_unsigned() {
+        let unsigned = |lon| normalize_longitude(lon, Grib2LonConvention::Unsigned);
+        // 0..360 input is unchanged, except 360 itself
+        for lon in [0.0, 0.5, 180.0, 190.0, 359.5] {
+            assert_eq!(unsigned(lon), lon);
+        }
+        assert_eq!(unsigned(360.0), 0.0);
+        assert_eq!(unsigned(720.0), 0.0);
+        // -180..180 input
+        for (lon, expected) in [(-180.0, 180.0), (-90.0, 270.0), (-0.5, 359.5), (45.0, 45.0)] {
+            assert_eq!(unsigned(lon), expected);
+        }
+        // A tiny negative longitude has a remainder that rounds up to exactly 360
+        assert_eq!((-1e-20f64).rem_euclid(360.0), 360.0);
+        assert_eq!(unsigned(-1e-20), 0.0);
+    }
+
+    #[test]
+    fn normalize_longitude_negative_lo1() {
+        // Columns of a grid starting at Lo1 = -10 with 5 degree steps
+        let columns: Vec<f64> = (0..5).map(|i| -10.0 + 5.0 * f64::from(i)).collect();
+        let normalized = |convention| -> Vec<f64> {
+            columns
+                .iter()
+                .map(|&lon| normalize_longitude(lon, convention))
+                .collect()
+        };
+        assert_eq!(
+            normalized(Grib2LonConvention::Signed),
+            [-10.0, -5.0, 0.0, 5.0, 10.0]
+        );
+        assert_eq!(
+            normalized(Grib2LonConvention::Unsigned),
+            [350.0, 355.0, 0.0, 5.0, 10.0]
+        );
+        // Every input lands in the convention's half-open range
+        for k in -1440..=1440 {
+            let lon = f64::from(k) * 0.5;
+            assert!((-180.0..180.0).contains(&normalize_longitude(lon, Grib2LonConvention::Signed)));
+            assert!((0.0..360.0).contains(&normalize_longitude(lon, Grib2LonConvention::Unsigned)));
+        }
+    }
+
+    #[test]
+    fn parameter_value_kinds() {
+        assert_eq!(
+            grib2_parameter_value_kind(0, 0, 0),
+            parameters::VALUE_CONTINUOUS
+        );
+        assert_eq!(
+            grib2_parameter_value_kind(0, 1, 19),
+            parameters::VALUE_CATEGORICAL
+        );
+        assert_eq!(
+            grib2_parameter_value_kind(0, 1, 192),
+            parameters::VALUE_CATEGORICAL
+        );
+        // Unmapped triples default to continuous
+        assert_eq!(
+            grib2_parameter_value_kind(0, 250, 250),
+            parameters::VALUE_CONTINUOUS
+        );
+    }
+
+    /// (value, is_missing) of the points in the first batch
+    fn read_values(reader: *mut Grib2Reader) -> Vec<(f64, bool)> {
+        let batch = grib2_read_batch(reader, 1024);
+        assert!(batch.error.is_null());
+        let points = if batch.count == 0 {
+            Vec::new()
+        } else {
+            unsafe { std::slice::from_raw_parts(batch.data, batch.count) }
+                .iter()
+                .map(|p| (p.value, p.is_missing))
+                .collect()
+        };
+        grib2_free_batch(batch);
+        points
+    }
+
+    #[test]
+    fn bitmap_masked_points_are_nan_or_skipped() {
+        // 3x2 grid with points 1 and 4 masked, the others valued 0, 2, 3 and 5
+        let bytes = include_bytes!("../../test/data/latlon_bitmap.grib2");
+
+        let reader = open_fixture(bytes);
+        let points = read_values(reader);
+        grib2_close(reader);
+        assert_eq!(points.len(), 6);
+        for (k, &(value, is_missing)) in points.iter().enumerate() {
+            let masked = k == 1 || k == 4;
+            assert_eq!(is_missing, masked);
+            assert_eq!(value.is_nan(), masked);
+            if !masked {
+                assert_eq!(value, k as f64);
+            }
+        }
+
+        let reader = open_fixture(bytes);
+        grib2_set_skip_missing(reader, true);
+        let points = read_values(reader);
+        grib2_close(reader);
+        assert_eq!(
+            points,
+            [(0.0, false), (2.0, false), (3.0, false), (5.0, false)]
+        );
+    }
+
+    #[test]
+    fn write_csv_fails_when_the_point_budget_truncates_it() {
+        let path = std::env::temp_dir().join(format!("grib2_budget_{}.csv", std::process::id()));
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+        let rows = || std::fs::read_to_string(&path).unwrap().lines().count() - 1;
+        // 3x2 grid, six points
+        let reader = open_fixture(include_bytes!("../../test/data/latlon_bitmap.grib2"));
+
+        grib2_set_point_budget(reader, 4);
+        let mut error = ptr::null_mut();
+        assert!(!grib2_write_csv(reader, c_path.as_ptr(), &mut error));
+        assert!(take_error(error).contains("point budget"));
+        assert_eq!(rows(), 4);
+
+        // for_each_point stops at the budget too, and says so
+        extern "C" fn count(_: *const Grib2DataPoint, seen: *mut c_void) -> bool {
+            unsafe { *(seen as *mut usize) += 1 };
+            true
+        }
+        grib2_reset(reader);
+        let mut seen = 0usize;
+        let delivered =
+            grib2_for_each_point(reader, Some(count), &mut seen as *mut usize as *mut c_void);
+        assert_eq!((delivered, seen), (4, 4));
+        assert!(grib2_budget_exceeded(reader));
+
+        grib2_set_point_budget(reader, 0);
+        let mut error = ptr::null_mut();
+        assert!(grib2_write_csv(reader, c_path.as_ptr(), &mut error));
+        assert!(error.is_null());
+        assert_eq!(rows(), 6);
+
+        grib2_close(reader);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn first_match_reports_a_corrupt_matching_message() {
+        // Temperature whose section 5 length runs past the message: the section 4
+        // header still matches, so this must fail as malformed rather than NotFound
+        let mut bytes = include_bytes!("../../test/data/latlon_bitmap.grib2").to_vec();
+        assert_eq!(bytes[147], 5);
+        bytes[143..147].copy_from_slice(&u32::MAX.to_be_bytes());
+        let path = std::env::temp_dir().join(format!("grib2_corrupt_{}.grib2", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+
+        let mut error = ptr::null_mut();
+        let mut code = Grib2ErrorCode::Ok;
+        let reader = grib2_open_first_match(c_path.as_ptr(), 0, 0, 0, &mut error, &mut code);
+        assert!(reader.is_null());
+        assert_eq!(code, Grib2ErrorCode::DecodeFailed);
+        assert!(take_error(error).starts_with("message 0: "));
+
+        // Intact, a filter nothing matches is still NotFound
+        std::fs::write(&path, include_bytes!("../../test/data/latlon_bitmap.grib2")).unwrap();
+        let mut error = ptr::null_mut();
+        let reader = grib2_open_first_match(c_path.as_ptr(), 0, 1, 1, &mut error, &mut code);
+        assert!(reader.is_null());
+        assert_eq!(code, Grib2ErrorCode::NotFound);
+        take_error(error);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn text_products_only_match_unset_parameter_filters() {
+        let temperature = MessageFilter::from_c(0, 0, 0);
+        let any = MessageFilter::from_c(0, -1, -1);
+        assert!(!temperature.matches(0, None, None));
+        assert!(any.matches(0, None, None));
+
+        // The bitmap fixture's section 4 starts at byte 109, relabelled as 4.254 text
+        let mut bytes = include_bytes!("../../test/data/latlon_bitmap.grib2").to_vec();
+        assert_eq!(bytes[113], 4);
+        bytes[116..118].copy_from_slice(&254u16.to_be_bytes());
+        let reader = Grib2Reader::from_reader(Cursor::new(bytes.clone()), &temperature).unwrap();
+        assert!(reader.messages.is_empty());
+        let mut source = Cursor::new(bytes);
+        assert!(next_match(&mut source, (0, 0), &temperature)
+            .unwrap()
+            .is_none());
+        assert!(next_match(&mut source, (0, 0), &any).unwrap().is_some());
+    }
+}
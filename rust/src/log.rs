@@ -0,0 +1,64 @@
+//! Diagnostics for messages the reader drops or can't decode
+//!
+//! Events go to `tracing` for Rust hosts and, once a C host registers one with
+//! `grib2_set_log_callback`, to that callback as formatted lines. Without the
+//! `tracing` feature the `log_event!` call sites compile to nothing.
+
+use std::ffi::{c_char, c_void, CString};
+use std::fmt::Arguments;
+use std::sync::{Mutex, PoisonError};
+
+/// Severity of a log line, in the order of `tracing::Level`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grib2LogLevel {
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+    Trace = 5,
+}
+
+/// Receives each log line with the user_data given at registration
+/// The message is only valid during the call; the callback must not unwind
+pub type Grib2LogCallback =
+    extern "C" fn(level: Grib2LogLevel, message: *const c_char, user_data: *mut c_void);
+
+#[derive(Clone, Copy)]
+struct Sink {
+    callback: Grib2LogCallback,
+    user_data: *mut c_void,
+}
+
+// user_data belongs to the host, which registers it for calls from any thread
+unsafe impl Send for Sink {}
+
+static SINK: Mutex<Option<Sink>> = Mutex::new(None);
+
+/// Replace the process-wide callback; None stops forwarding
+pub(crate) fn set_callback(callback: Option<Grib2LogCallback>, user_data: *mut c_void) {
+    let sink = callback.map(|callback| Sink {
+        callback,
+        user_data,
+    });
+    *SINK.lock().unwrap_or_else(PoisonError::into_inner) = sink;
+}
+
+/// Emit one event; the line is only formatted when a callback is registered
+pub(crate) fn emit(level: Grib2LogLevel, message: Arguments) {
+    match level {
+        Grib2LogLevel::Error => tracing::error!("{message}"),
+        Grib2LogLevel::Warn => tracing::warn!("{message}"),
+        Grib2LogLevel::Info => tracing::info!("{message}"),
+        Grib2LogLevel::Debug => tracing::debug!("{message}"),
+        Grib2LogLevel::Trace => tracing::trace!("{message}"),
+    }
+    // Copied out so a callback that re-registers doesn't deadlock
+    let sink = *SINK.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(sink) = sink {
+        let mut bytes = message.to_string().into_bytes();
+        bytes.retain(|b| *b != 0);
+        let line = CString::new(bytes).unwrap_or_default();
+        (sink.callback)(level, line.as_ptr(), sink.user_data);
+    }
+}
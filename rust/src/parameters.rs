@@ -0,0 +1,270 @@
+//! Parameter names and units from WMO GRIB2 code table 4.2
+//!
+//! Covers the fields commonly found in GFS, HRRR, ICON and ECMWF open data.
+//! Abbreviations follow the NCEP/wgrib2 convention. Entries in the 192-254
+//! range are NCEP local definitions that appear in NOAA products.
+//!
+//! WMO only ever appends to code table 4.2, so an entry below 192 means the same
+//! in every master table version (section 1, octet 10). Local entries mean what
+//! the producing centre's local tables say, so `lookup_in` resolves them only
+//! for NCEP messages that declare local tables (octet 11 of 1-254).
+
+use super::Grib2Identification;
+use std::ffi::CStr;
+
+/// One entry of code table 4.2
+pub(crate) struct Parameter {
+    pub discipline: u8,
+    pub category: u8,
+    pub number: u8,
+    pub abbrev: &'static CStr,
+    pub name: &'static CStr,
+    /// WMO unit notation ("K", "m s-1", "kg m-2")
+    pub units: &'static CStr,
+}
+
+/// Returned for triples missing from the table
+pub(crate) const UNKNOWN: &CStr = c"unknown";
+
+const fn p(
+    discipline: u8,
+    category: u8,
+    number: u8,
+    abbrev: &'static CStr,
+    name: &'static CStr,
+    units: &'static CStr,
+) -> Parameter {
+    Parameter {
+        discipline,
+        category,
+        number,
+        abbrev,
+        name,
+        units,
+    }
+}
+
+/// Sorted by (discipline, category, number); add new entries in place
+/// Columns: discipline, category, number, abbreviation, name, units
+#[rustfmt::skip]
+const PARAMETERS: &[Parameter] = &[
+    // Meteorological: temperature
+    p(0, 0, 0, c"TMP", c"Temperature", c"K"),
+    p(0, 0, 1, c"VTMP", c"Virtual temperature", c"K"),
+    p(0, 0, 2, c"POT", c"Potential temperature", c"K"),
+    p(0, 0, 3, c"EPOT", c"Pseudo-adiabatic potential temperature", c"K"),
+    p(0, 0, 4, c"TMAX", c"Maximum temperature", c"K"),
+    p(0, 0, 5, c"TMIN", c"Minimum temperature", c"K"),
+    p(0, 0, 6, c"DPT", c"Dew point temperature", c"K"),
+    p(0, 0, 7, c"DEPR", c"Dew point depression", c"K"),
+    p(0, 0, 8, c"LAPR", c"Lapse rate", c"K m-1"),
+    p(0, 0, 9, c"TMPA", c"Temperature anomaly", c"K"),
+    p(0, 0, 10, c"LHTFL", c"Latent heat net flux", c"W m-2"),
+    p(0, 0, 11, c"SHTFL", c"Sensible heat net flux", c"W m-2"),
+    p(0, 0, 15, c"VPTMP", c"Virtual potential temperature", c"K"),
+    p(0, 0, 17, c"SKINT", c"Skin temperature", c"K"),
+    p(0, 0, 21, c"APTMP", c"Apparent temperature", c"K"),
+    // Meteorological: moisture
+    p(0, 1, 0, c"SPFH", c"Specific humidity", c"kg kg-1"),
+    p(0, 1, 1, c"RH", c"Relative humidity", c"%"),
+    p(0, 1, 2, c"MIXR", c"Humidity mixing ratio", c"kg kg-1"),
+    p(0, 1, 3, c"PWAT", c"Precipitable water", c"kg m-2"),
+    p(0, 1, 4, c"VAPP", c"Vapour pressure", c"Pa"),
+    p(0, 1, 5, c"SATD", c"Saturation deficit", c"Pa"),
+    p(0, 1, 6, c"EVP", c"Evaporation", c"kg m-2"),
+    p(0, 1, 7, c"PRATE", c"Precipitation rate", c"kg m-2 s-1"),
+    p(0, 1, 8, c"APCP", c"Total precipitation", c"kg m-2"),
+    p(0, 1, 9, c"NCPCP", c"Large-scale precipitation", c"kg m-2"),
+    p(0, 1, 10, c"ACPCP", c"Convective precipitation", c"kg m-2"),
+    p(0, 1, 11, c"SNOD", c"Snow depth", c"m"),
+    p(0, 1, 12, c"SRWEQ", c"Snowfall rate water equivalent", c"kg m-2 s-1"),
+    p(0, 1, 13, c"WEASD", c"Water equivalent of accumulated snow depth", c"kg m-2"),
+    p(0, 1, 19, c"PTYPE", c"Precipitation type", c"code table 4.201"),
+    p(0, 1, 22, c"CLWMR", c"Cloud mixing ratio", c"kg kg-1"),
+    p(0, 1, 29, c"ASNOW", c"Total snowfall", c"m"),
+    p(0, 1, 37, c"CPRAT", c"Convective precipitation rate", c"kg m-2 s-1"),
+    p(0, 1, 42, c"SNOWC", c"Snow cover", c"%"),
+    p(0, 1, 60, c"SDWE", c"Snow depth water equivalent", c"kg m-2"),
+    p(0, 1, 192, c"CRAIN", c"Categorical rain", c"code table 4.222"),
+    p(0, 1, 193, c"CFRZR", c"Categorical freezing rain", c"code table 4.222"),
+    p(0, 1, 194, c"CICEP", c"Categorical ice pellets", c"code table 4.222"),
+    p(0, 1, 195, c"CSNOW", c"Categorical snow", c"code table 4.222"),
+    // Meteorological: momentum
+    p(0, 2, 0, c"WDIR", c"Wind direction (from which blowing)", c"degree true"),
+    p(0, 2, 1, c"WIND", c"Wind speed", c"m s-1"),
+    p(0, 2, 2, c"UGRD", c"u-component of wind", c"m s-1"),
+    p(0, 2, 3, c"VGRD", c"v-component of wind", c"m s-1"),
+    p(0, 2, 4, c"STRM", c"Stream function", c"m2 s-1"),
+    p(0, 2, 5, c"VPOT", c"Velocity potential", c"m2 s-1"),
+    p(0, 2, 8, c"VVEL", c"Vertical velocity (pressure)", c"Pa s-1"),
+    p(0, 2, 9, c"DZDT", c"Vertical velocity (geometric)", c"m s-1"),
+    p(0, 2, 10, c"ABSV", c"Absolute vorticity", c"s-1"),
+    p(0, 2, 11, c"ABSD", c"Absolute divergence", c"s-1"),
+    p(0, 2, 12, c"RELV", c"Relative vorticity", c"s-1"),
+    p(0, 2, 13, c"RELD", c"Relative divergence", c"s-1"),
+    p(0, 2, 22, c"GUST", c"Wind speed (gust)", c"m s-1"),
+    // Meteorological: mass
+    p(0, 3, 0, c"PRES", c"Pressure", c"Pa"),
+    p(0, 3, 1, c"PRMSL", c"Pressure reduced to MSL", c"Pa"),
+    p(0, 3, 2, c"PTEND", c"Pressure tendency", c"Pa s-1"),
+    p(0, 3, 3, c"ICAHT", c"ICAO standard atmosphere reference height", c"m"),
+    p(0, 3, 4, c"GP", c"Geopotential", c"m2 s-2"),
+    p(0, 3, 5, c"HGT", c"Geopotential height", c"gpm"),
+    p(0, 3, 6, c"DIST", c"Geometric height", c"m"),
+    p(0, 3, 10, c"DEN", c"Density", c"kg m-3"),
+    p(0, 3, 18, c"HPBL", c"Planetary boundary layer height", c"m"),
+    p(0, 3, 196, c"HPBL", c"Planetary boundary layer height", c"m"),
+    // Meteorological: short-wave radiation
+    p(0, 4, 0, c"NSWRS", c"Net short-wave radiation flux (surface)", c"W m-2"),
+    p(0, 4, 7, c"DSWRF", c"Downward short-wave radiation flux", c"W m-2"),
+    p(0, 4, 8, c"USWRF", c"Upward short-wave radiation flux", c"W m-2"),
+    // Meteorological: long-wave radiation
+    p(0, 5, 0, c"NLWRS", c"Net long-wave radiation flux (surface)", c"W m-2"),
+    p(0, 5, 3, c"DLWRF", c"Downward long-wave radiation flux", c"W m-2"),
+    p(0, 5, 4, c"ULWRF", c"Upward long-wave radiation flux", c"W m-2"),
+    // Meteorological: cloud
+    p(0, 6, 1, c"TCDC", c"Total cloud cover", c"%"),
+    p(0, 6, 3, c"LCDC", c"Low cloud cover", c"%"),
+    p(0, 6, 4, c"MCDC", c"Medium cloud cover", c"%"),
+    p(0, 6, 5, c"HCDC", c"High cloud cover", c"%"),
+    // Meteorological: thermodynamic stability
+    p(0, 7, 0, c"PLI", c"Parcel lifted index (to 500 hPa)", c"K"),
+    p(0, 7, 1, c"BLI", c"Best lifted index (to 500 hPa)", c"K"),
+    p(0, 7, 6, c"CAPE", c"Convective available potential energy", c"J kg-1"),
+    p(0, 7, 7, c"CIN", c"Convective inhibition", c"J kg-1"),
+    p(0, 7, 8, c"HLCY", c"Storm relative helicity", c"m2 s-2"),
+    p(0, 7, 10, c"LFTX", c"Surface lifted index", c"K"),
+    p(0, 7, 11, c"4LFTX", c"Best (4-layer) lifted index", c"K"),
+    // Meteorological: trace gases
+    p(0, 14, 0, c"TOZNE", c"Total ozone", c"DU"),
+    // Meteorological: forecast radar imagery
+    p(0, 16, 195, c"REFD", c"Reflectivity", c"dB"),
+    p(0, 16, 196, c"REFC", c"Composite reflectivity", c"dB"),
+    // Meteorological: physical atmospheric properties
+    p(0, 19, 0, c"VIS", c"Visibility", c"m"),
+    // Land surface: vegetation/biomass
+    p(2, 0, 0, c"LAND", c"Land cover (1 = land, 0 = sea)", c"Proportion"),
+    p(2, 0, 2, c"TSOIL", c"Soil temperature", c"K"),
+    p(2, 0, 3, c"SOILM", c"Soil moisture content", c"kg m-2"),
+    p(2, 0, 192, c"SOILW", c"Volumetric soil moisture content", c"Proportion"),
+    // Oceanographic: waves
+    p(10, 0, 3, c"HTSGW", c"Significant height of combined wind waves and swell", c"m"),
+    p(10, 0, 4, c"WVDIR", c"Direction of wind waves", c"degree true"),
+    p(10, 0, 5, c"WVHGT", c"Significant height of wind waves", c"m"),
+    // Oceanographic: surface properties
+    p(10, 3, 0, c"WTMP", c"Water temperature", c"K"),
+];
+
+/// Originating centre (common code table C-11) whose local entries the table holds
+const NCEP: u16 = 7;
+
+/// Whether any part of a triple falls in the ranges tables 0.0, 4.1 and 4.2 leave
+/// to local use
+fn is_local(discipline: u8, category: u8, number: u8) -> bool {
+    [discipline, category, number]
+        .iter()
+        .any(|code| (192..=254).contains(code))
+}
+
+/// Find the table entry for a triple read in a message with this section 1
+/// Master table version 255 means only local tables are in use, which we don't
+/// know for any triple
+pub(crate) fn lookup_in(
+    discipline: u8,
+    category: u8,
+    number: u8,
+    identification: &Grib2Identification,
+) -> Option<&'static Parameter> {
+    let defined = match identification.master_table_version {
+        255 => false,
+        _ if is_local(discipline, category, number) => {
+            identification.centre == NCEP && !matches!(identification.local_table_version, 0 | 255)
+        }
+        _ => true,
+    };
+    defined
+        .then(|| lookup(discipline, category, number))
+        .flatten()
+}
+
+/// Find the table entry for a (discipline, category, number) triple, taking local
+/// entries as NCEP's
+pub(crate) fn lookup(discipline: u8, category: u8, number: u8) -> Option<&'static Parameter> {
+    PARAMETERS
+        .binary_search_by_key(&(discipline, category, number), |p| {
+            (p.discipline, p.category, p.number)
+        })
+        .ok()
+        .map(|i| &PARAMETERS[i])
+}
+
+/// Stable 32-bit key of a (discipline, category, number) triple: 0x00DDCCNN
+pub(crate) fn key(discipline: u8, category: u8, number: u8) -> u32 {
+    u32::from_be_bytes([0, discipline, category, number])
+}
+
+/// The triple behind a key from `key`; None when the top byte is set
+pub(crate) fn unpack_key(key: u32) -> Option<(u8, u8, u8)> {
+    match key.to_be_bytes() {
+        [0, discipline, category, number] => Some((discipline, category, number)),
+        _ => None,
+    }
+}
+
+/// Whether values of the parameter are entries of a code table (1 = rain, 3 = freezing
+/// rain, ...) rather than quantities
+pub(crate) fn is_categorical(param: &Parameter) -> bool {
+    param.units.to_bytes().starts_with(b"code table")
+}
+
+/// Value kinds returned by grib2_parameter_value_kind (GRIB2_VALUE_* in the header)
+pub(crate) const VALUE_CONTINUOUS: u8 = 0;
+pub(crate) const VALUE_CATEGORICAL: u8 = 1;
+
+/// How values of the parameter should be typed, from the semantics of its units:
+/// code-table entries are categories and everything else is a quantity
+pub(crate) fn value_kind(param: &Parameter) -> u8 {
+    if is_categorical(param) {
+        VALUE_CATEGORICAL
+    } else {
+        VALUE_CONTINUOUS
+    }
+}
+
+/// unit_mode flags for grib2_read_batch_converted (GRIB2_UNITS_* in the header)
+pub(crate) const UNITS_CELSIUS: u32 = 1;
+pub(crate) const UNITS_HPA: u32 = 2;
+pub(crate) const UNITS_FEET: u32 = 4;
+
+/// Linear unit conversion: value * scale + offset
+#[derive(Clone, Copy)]
+pub(crate) struct Conversion {
+    scale: f64,
+    offset: f64,
+}
+
+impl Conversion {
+    pub fn apply(self, value: f64) -> f64 {
+        value * self.scale + self.offset
+    }
+}
+
+/// Temperature entries that are differences, so Kelvin and Celsius agree
+fn is_temperature_difference(p: &Parameter) -> bool {
+    matches!(
+        (p.discipline, p.category, p.number),
+        (0, 0, 7) | (0, 0, 9) | (0, 7, 0) | (0, 7, 1) | (0, 7, 10) | (0, 7, 11)
+    )
+}
+
+/// Conversion selected by unit_mode for a parameter, or None to keep native units
+pub(crate) fn conversion(param: &Parameter, unit_mode: u32) -> Option<Conversion> {
+    let (flag, scale, offset) = match param.units.to_bytes() {
+        b"K" if is_temperature_difference(param) => return None,
+        b"K" => (UNITS_CELSIUS, 1.0, -273.15),
+        b"Pa" => (UNITS_HPA, 0.01, 0.0),
+        b"m" | b"gpm" => (UNITS_FEET, 1.0 / 0.3048, 0.0),
+        _ => return None,
+    };
+    (unit_mode & flag != 0).then_some(Conversion { scale, offset })
+}
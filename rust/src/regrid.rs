@@ -0,0 +1,256 @@
+//! Resampling of arbitrary grids onto a regular lat/lon mesh
+//!
+//! Source points are bucketed into cells about one search radius wide, so each
+//! target node only looks at the 3x3 buckets around it.
+
+use std::borrow::Cow;
+
+use super::haversine_km;
+
+/// Largest mesh `regrid` builds, about a 0.03 degree global grid
+const MAX_NODES: usize = 1 << 26;
+
+/// How target nodes are filled from nearby source points
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grib2RegridMethod {
+    Nearest = 0,
+    InverseDistance = 1, // weights 1/d^2 over all points within the search radius
+}
+
+/// Regular lat/lon mesh produced by `regrid`, rows north to south, columns west to east
+pub(crate) struct RegularMesh {
+    pub ni: usize, // columns
+    pub nj: usize, // rows
+    pub points: Vec<(f64, f64, f64)>,
+}
+
+/// Spatial hash of source points in buckets of `size` degrees
+struct Buckets {
+    lat0: f64,
+    lon0: f64,
+    size: f64,
+    rows: usize,
+    cols: usize,
+    wrap: bool, // the source spans all longitudes, so the columns are cyclic
+    cells: Vec<Vec<u32>>,
+}
+
+impl Buckets {
+    fn new(points: &[(f64, f64, f64)], extent: Extent, size: f64, wrap: bool) -> Self {
+        let rows = ((extent.max_lat - extent.min_lat) / size).floor() as usize + 1;
+        let cols = ((extent.max_lon - extent.min_lon) / size).floor() as usize + 1;
+        let mut buckets = Buckets {
+            lat0: extent.min_lat,
+            lon0: extent.min_lon,
+            size,
+            rows,
+            cols,
+            wrap,
+            cells: vec![Vec::new(); rows * cols],
+        };
+        for (i, &(lat, lon, _)) in points.iter().enumerate() {
+            let (row, col) = buckets.cell(lat, lon);
+            buckets.cells[row * cols + col].push(i as u32);
+        }
+        buckets
+    }
+
+    fn cell(&self, lat: f64, lon: f64) -> (usize, usize) {
+        let row = ((lat - self.lat0) / self.size).floor().max(0.0) as usize;
+        let col = ((lon - self.lon0) / self.size).floor().max(0.0) as usize;
+        (row.min(self.rows - 1), col.min(self.cols - 1))
+    }
+
+    /// Indices of the source points in the 3x3 buckets around (lat, lon)
+    fn around(&self, lat: f64, lon: f64) -> impl Iterator<Item = u32> + '_ {
+        let (row, col) = self.cell(lat, lon);
+        let rows = row.saturating_sub(1)..=(row + 1).min(self.rows - 1);
+        rows.flat_map(move |r| {
+            let cols: Vec<usize> = match (self.wrap, self.cols) {
+                (true, n) if n >= 3 => vec![(col + n - 1) % n, col, (col + 1) % n],
+                _ => (col.saturating_sub(1)..=(col + 1).min(self.cols - 1)).collect(),
+            };
+            cols.into_iter()
+                .flat_map(move |c| self.cells[r * self.cols + c].iter().copied())
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Extent {
+    min_lat: f64,
+    max_lat: f64,
+    min_lon: f64,
+    max_lon: f64,
+}
+
+impl Extent {
+    fn of(points: &[(f64, f64, f64)], frame: fn(f64) -> f64) -> Option<Self> {
+        points
+            .iter()
+            .fold(None, |acc: Option<Extent>, &(lat, lon, _)| {
+                let lon = frame(lon);
+                let e = acc.unwrap_or(Extent {
+                    min_lat: lat,
+                    max_lat: lat,
+                    min_lon: lon,
+                    max_lon: lon,
+                });
+                Some(Extent {
+                    min_lat: e.min_lat.min(lat),
+                    max_lat: e.max_lat.max(lat),
+                    min_lon: e.min_lon.min(lon),
+                    max_lon: e.max_lon.max(lon),
+                })
+            })
+    }
+}
+
+fn signed(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+fn unsigned(lon: f64) -> f64 {
+    lon.rem_euclid(360.0)
+}
+
+/// Resample scattered (lat, lon, value) points onto a regular mesh covering their extent
+/// Nodes sit on multiples of the steps; nodes with no source point within the search
+/// radius, or only masked ones, get NaN. Returns None for non-positive steps, no points
+/// or a mesh of more than MAX_NODES nodes
+pub(crate) fn regrid(
+    points: &[(f64, f64, f64)],
+    lat_step: f64,
+    lon_step: f64,
+    method: Grib2RegridMethod,
+) -> Option<RegularMesh> {
+    if !(lat_step > 0.0 && lon_step > 0.0) {
+        return None;
+    }
+    // Take the extent in whichever longitude range keeps the grid in one piece, so
+    // a regional grid across the antimeridian (or the prime meridian) isn't
+    // stretched to 360 degrees; global grids keep the longitudes as given
+    let span = |e: &Extent| e.max_lon - e.min_lon;
+    let mut extent = Extent::of(points, |lon| lon)?;
+    let mut frame = None;
+    for shift in [signed as fn(f64) -> f64, unsigned] {
+        let shifted = Extent::of(points, shift)?;
+        if span(&shifted) < span(&extent) {
+            (extent, frame) = (shifted, Some(shift));
+        }
+    }
+    // Nodes go back to the convention of the source longitudes
+    let output = match frame {
+        None => |lon| lon,
+        Some(_) if points.iter().any(|p| p.1 < 0.0) => signed,
+        Some(_) => unsigned,
+    };
+    let points: Cow<[(f64, f64, f64)]> = match frame {
+        None => Cow::Borrowed(points),
+        Some(shift) => points
+            .iter()
+            .map(|&(lat, lon, value)| (lat, shift(lon), value))
+            .collect(),
+    };
+
+    // Average source spacing, so coarse sources still reach every target node
+    let lat_span = extent.max_lat - extent.min_lat;
+    let lon_span = extent.max_lon - extent.min_lon;
+    let spacing = (lat_span.max(lat_step) * lon_span.max(lon_step) / points.len() as f64).sqrt();
+    let radius = lat_step.max(lon_step).max(1.5 * spacing);
+    // A source within a couple of spacings of 360 degrees wide wraps around
+    let wrap = lon_span + 2.0 * spacing >= 360.0;
+
+    let buckets = Buckets::new(&points, extent, radius, wrap);
+
+    let first_lat = (extent.max_lat / lat_step).floor() * lat_step;
+    let first_lon = (extent.min_lon / lon_step).ceil() * lon_step;
+    // Counts saturate for tiny steps, for the node cap below to reject
+    let count = |span: f64, step: f64| ((span / step + 1e-9).floor() as usize).saturating_add(1);
+    let nj = count(first_lat - extent.min_lat, lat_step);
+    let mut ni = count(extent.max_lon - first_lon, lon_step);
+    if wrap {
+        ni = ni.min((360.0 / lon_step).round() as usize);
+    }
+    let radius_km = radius * 111.2;
+
+    let nodes = ni.checked_mul(nj).filter(|&n| n <= MAX_NODES)?;
+
+    let mut mesh = Vec::with_capacity(nodes);
+    for j in 0..nj {
+        let lat = first_lat - j as f64 * lat_step;
+        for i in 0..ni {
+            let lon = first_lon + i as f64 * lon_step;
+            let candidates = buckets.around(lat, lon).filter_map(|k| {
+                let (plat, plon, value) = points[k as usize];
+                if value.is_nan() {
+                    return None;
+                }
+                let d = haversine_km(lat, lon, plat, plon);
+                (d <= radius_km).then_some((d, value))
+            });
+            let value = match method {
+                Grib2RegridMethod::Nearest => candidates
+                    .min_by(|a, b| a.0.total_cmp(&b.0))
+                    .map_or(f64::NAN, |(_, value)| value),
+                Grib2RegridMethod::InverseDistance => inverse_distance(candidates),
+            };
+            mesh.push((lat, output(lon), value));
+        }
+    }
+
+    Some(RegularMesh {
+        ni,
+        nj,
+        points: mesh,
+    })
+}
+
+/// Weighted mean with weights 1/d^2; a source point on the node wins outright
+fn inverse_distance(candidates: impl Iterator<Item = (f64, f64)>) -> f64 {
+    let (mut weighted, mut total) = (0.0, 0.0);
+    for (d, value) in candidates {
+        if d < 1e-6 {
+            return value;
+        }
+        let w = 1.0 / (d * d);
+        weighted += w * value;
+        total += w;
+    }
+    if total > 0.0 {
+        weighted / total
+    } else {
+        f64::NAN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_meshes_over_the_node_cap() {
+        let points = [(-90.0, -180.0, 1.0), (90.0, 179.0, 2.0)];
+        assert!(regrid(&points, 0.001, 0.001, Grib2RegridMethod::Nearest).is_none());
+        assert!(regrid(&points, 1e-300, 1e-300, Grib2RegridMethod::Nearest).is_none());
+        assert!(regrid(&points, 10.0, 10.0, Grib2RegridMethod::Nearest).is_some());
+    }
+
+    #[test]
+    fn keeps_a_grid_across_the_antimeridian_in_one_piece() {
+        // 170E to 170W in signed longitudes, one degree apart
+        let points: Vec<_> = (0..=20)
+            .flat_map(|i| {
+                let lon = signed(170.0 + f64::from(i));
+                [(0.0, lon, f64::from(i)), (1.0, lon, f64::from(i))]
+            })
+            .collect();
+        let mesh = regrid(&points, 1.0, 1.0, Grib2RegridMethod::Nearest).unwrap();
+        assert_eq!((mesh.ni, mesh.nj), (21, 2));
+        assert!(mesh.points.iter().all(|p| !p.2.is_nan()));
+        assert_eq!(mesh.points[0].1, 170.0);
+        assert_eq!(mesh.points[20].1, -170.0);
+        assert_eq!(mesh.points[11].2, 11.0);
+    }
+}
@@ -684,9 +684,10 @@ static void GfsForecastScan(ClientContext &context, TableFunctionInput &data,
 
     // Parse GRIB from memory
     char *error = nullptr;
-    gstate.reader = grib2_open_from_bytes(
+    // http_data is only cleared after the reader is closed
+    gstate.reader = grib2_open_from_bytes_borrowed(
         reinterpret_cast<const uint8_t *>(gstate.http_data.data()),
-        gstate.http_data.size(), &error);
+        gstate.http_data.size(), &error, nullptr);
 
     if (!gstate.reader) {
       string err_msg = error ? string(error) : "Unknown error";
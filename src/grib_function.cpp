@@ -4,12 +4,15 @@
 #include "duckdb/common/exception.hpp"
 #include "duckdb/common/http_util.hpp"
 #include "duckdb/common/string_util.hpp"
+#include "duckdb/common/types/timestamp.hpp"
 #include "duckdb/common/types/value.hpp"
 #include "duckdb/function/table_function.hpp"
 #include "duckdb/main/client_context.hpp"
 #include "duckdb/main/connection.hpp"
 #include "grib2_ffi.h"
 
+#include <atomic>
+
 namespace duckdb {
 
 // Helper to check if path is an HTTP URL
@@ -331,6 +334,15 @@ static uint8_t ParameterToEnumIndex(uint8_t disc, uint8_t cat, uint8_t num) {
   return 48; // Unknown
 }
 
+// valid_time column value; the library reports an unknown time as 0
+static Value ValidTimeValue(int64_t seconds) {
+  if (seconds == 0) {
+    return Value(LogicalType::TIMESTAMP_TZ);
+  }
+  return Value::TIMESTAMPTZ(
+      timestamp_tz_t(Timestamp::FromEpochSeconds(seconds)));
+}
+
 // Helper to create ENUM types
 static void CreateEnumTypes(GribBindData &bind_data) {
   Vector disc_vec(LogicalType::VARCHAR, DISCIPLINE_VALUES.size());
@@ -362,6 +374,7 @@ static void CreateEnumTypes(GribBindData &bind_data) {
 static Grib2Reader *OpenGribSource(ClientContext &context, const string &path,
                                    string &http_data_out) {
   char *error = nullptr;
+  Grib2ErrorCode error_code = GRIB2_ERROR_OK;
   Grib2Reader *reader = nullptr;
 
   if (IsHttpUrl(path)) {
@@ -381,17 +394,30 @@ static Grib2Reader *OpenGribSource(ClientContext &context, const string &path,
 
     http_data_out = response->body;
 
-    reader = grib2_open_from_bytes(
+    // http_data_out is owned by the scan state and outlives the reader
+    reader = grib2_open_from_bytes_borrowed(
         reinterpret_cast<const uint8_t *>(http_data_out.data()),
-        http_data_out.size(), &error);
+        http_data_out.size(), &error, &error_code);
   } else {
-    reader = grib2_open_with_error(path.c_str(), &error);
+    reader = grib2_open_with_error(path.c_str(), &error, &error_code);
   }
 
   if (!reader) {
     string error_msg = error ? error : "Unknown error";
     if (error)
       grib2_free_error(error);
+    if (error_code == GRIB2_ERROR_NOT_GRIB) {
+      throw InvalidInputException("Not a GRIB2 source: " + path + " (" +
+                                  error_msg + ")");
+    }
+    if (error_code == GRIB2_ERROR_EMPTY) {
+      throw InvalidInputException("Empty GRIB2 source: " + path + " (" +
+                                  error_msg + ")");
+    }
+    if (error_code == GRIB2_ERROR_TRUNCATED) {
+      throw IOException("Incomplete GRIB2 source (interrupted download?): " +
+                        path + " (" + error_msg + ")");
+    }
     throw IOException("Failed to open GRIB source: " + error_msg);
   }
 
@@ -404,8 +430,12 @@ static Grib2Reader *OpenGribSource(ClientContext &context, const string &path,
 
 struct GribGlobalState : public GlobalTableFunctionState {
   Grib2Reader *reader = nullptr;
-  idx_t current_file_idx = 0;
-  idx_t total_points = 0;
+  // Read by GribProgress from the client thread while the scan runs, so kept
+  // as atomics instead of asking the reader, which the scan may close or hold
+  std::atomic<idx_t> current_file_idx{0};
+  std::atomic<idx_t> total_points{0};
+  std::atomic<idx_t> file_points{0};
+  std::atomic<idx_t> file_consumed{0};
   idx_t rows_returned = 0;
   idx_t limit_from_query = 0;
   bool finished = false;
@@ -427,7 +457,9 @@ struct GribGlobalState : public GlobalTableFunctionState {
     }
     http_data.clear();
     reader = OpenGribSource(*context_ptr, path, http_data);
-    total_points += grib2_total_points(reader);
+    file_consumed = 0;
+    file_points = grib2_total_points(reader);
+    total_points += file_points.load();
     return true;
   }
 };
@@ -466,15 +498,17 @@ static unique_ptr<FunctionData> GribBind(ClientContext &context,
 
   CreateEnumTypes(*bind_data);
 
-  names = {"latitude",      "longitude", "value",         "discipline",
-           "surface",       "parameter", "forecast_time", "surface_value",
-           "message_index", "file_index"};
+  names = {"latitude",      "longitude",        "value",
+           "discipline",    "surface",          "parameter",
+           "forecast_time", "surface_value",    "message_index",
+           "file_index",    "submessage_index", "valid_time"};
 
   return_types = {LogicalType::DOUBLE,     LogicalType::DOUBLE,
                   LogicalType::DOUBLE,     bind_data->discipline_type,
                   bind_data->surface_type, bind_data->parameter_type,
                   LogicalType::BIGINT,     LogicalType::DOUBLE,
-                  LogicalType::UINTEGER,   LogicalType::UINTEGER};
+                  LogicalType::UINTEGER,   LogicalType::UINTEGER,
+                  LogicalType::UINTEGER,   LogicalType::TIMESTAMP_TZ};
 
   return std::move(bind_data);
 }
@@ -588,10 +622,13 @@ static void GribScan(ClientContext &context, TableFunctionInput &data,
     output.SetValue(7, i, Value::DOUBLE(point.surface_value));
     output.SetValue(8, i, Value::UINTEGER(point.message_index));
     output.SetValue(9, i, Value::UINTEGER(static_cast<uint32_t>(current_file)));
+    output.SetValue(10, i, Value::UINTEGER(point.submessage_index));
+    output.SetValue(11, i, ValidTimeValue(point.valid_time));
   }
 
   output.SetCardinality(batch.count);
   state.rows_returned += batch.count;
+  state.file_consumed = grib2_points_consumed(state.reader);
   grib2_free_batch(batch);
 }
 
@@ -603,8 +640,16 @@ static double GribProgress(ClientContext &context,
 
   if (state.total_points == 0)
     return -1.0;
-  double file_progress = static_cast<double>(state.current_file_idx) /
-                         static_cast<double>(bind_data.file_paths.size());
+  // Partial progress within the open file from the points streamed so far
+  double within_file = 0.0;
+  idx_t file_points = state.file_points;
+  if (file_points > 0) {
+    within_file = std::min(1.0, static_cast<double>(state.file_consumed) /
+                                    static_cast<double>(file_points));
+  }
+  double file_progress =
+      (static_cast<double>(state.current_file_idx) + within_file) /
+      static_cast<double>(bind_data.file_paths.size());
   return file_progress * 100.0;
 }
 
@@ -648,13 +693,15 @@ static unique_ptr<FunctionData> GribInOutBind(ClientContext &context,
   // Output columns (no file_index needed - LATERAL handles row correlation)
   names = {"latitude",      "longitude",     "value",
            "discipline",    "surface",       "parameter",
-           "forecast_time", "surface_value", "message_index"};
+           "forecast_time", "surface_value", "message_index",
+           "submessage_index", "valid_time"};
 
   return_types = {LogicalType::DOUBLE,     LogicalType::DOUBLE,
                   LogicalType::DOUBLE,     bind_data->discipline_type,
                   bind_data->surface_type, bind_data->parameter_type,
                   LogicalType::BIGINT,     LogicalType::DOUBLE,
-                  LogicalType::UINTEGER};
+                  LogicalType::UINTEGER,   LogicalType::UINTEGER,
+                  LogicalType::TIMESTAMP_TZ};
 
   return std::move(bind_data);
 }
@@ -748,6 +795,8 @@ static OperatorResultType GribInOutFunction(ExecutionContext &context,
     output.SetValue(6, i, Value::BIGINT(point.forecast_time));
     output.SetValue(7, i, Value::DOUBLE(point.surface_value));
     output.SetValue(8, i, Value::UINTEGER(point.message_index));
+    output.SetValue(9, i, Value::UINTEGER(point.submessage_index));
+    output.SetValue(10, i, ValidTimeValue(point.valid_time));
   }
 
   output.SetCardinality(batch.count);
@@ -8,6 +8,22 @@
 extern "C" {
 #endif
 
+// Version of the layout of the structs and signatures below; must equal
+// grib2_abi_version() of the linked library, checked when the extension loads
+#define GRIB2_ABI_VERSION 5
+uint32_t grib2_abi_version(void);
+
+// What a message holds, the kind field of points and message info
+typedef enum {
+  GRIB2_KIND_ANALYSIS = 0,
+  GRIB2_KIND_FORECAST = 1, // deterministic, also ensemble means and spreads
+  GRIB2_KIND_ENSEMBLE_CONTROL = 2,
+  GRIB2_KIND_ENSEMBLE_PERTURBED = 3,
+  GRIB2_KIND_PROBABILITY = 4,
+  GRIB2_KIND_CLIMATOLOGY = 5,
+  GRIB2_KIND_UNKNOWN = 255,
+} Grib2MessageKind;
+
 // Data point from GRIB2 file
 typedef struct {
   double latitude;
@@ -17,9 +33,24 @@ typedef struct {
   uint8_t parameter_category;
   uint8_t parameter_number;
   int64_t forecast_time;
+  int64_t reference_time;
   uint8_t surface_type;
-  double surface_value;
-  uint32_t message_index;
+  double surface_value; // code table 4.5 units, e.g. Pa for isobaric levels
+  uint8_t surface_type_2; // 255 when the level is not a layer
+  double surface_value_2;
+  uint32_t message_index;    // GRIB message number within the file
+  uint32_t submessage_index; // field number within the message
+  bool is_missing;           // masked by the bitmap, value is NaN
+  int32_t ensemble_member;   // perturbation number, -1 if not an ensemble member
+  uint8_t statistical_process; // code table 4.10, 255 if instantaneous
+  int64_t time_range_seconds;  // accumulation/averaging period, 0 if instantaneous
+  uint8_t kind;                // Grib2MessageKind
+  int32_t probability_type; // code table 4.9, -1 if not a probability product
+  double threshold_lower;   // NaN when missing or not a probability product
+  double threshold_upper;
+  int32_t percentile; // 0-100 for percentile products, -1 otherwise
+  uint32_t source_index; // file within grib2_open_multi, 0 otherwise
+  int64_t valid_time; // unix seconds, end of interval if statistical; 0 unknown
 } Grib2DataPoint;
 
 // Batch of data points for streaming
@@ -30,22 +61,566 @@ typedef struct {
   char *error;
 } Grib2Batch;
 
+// Single-precision data point, halves the transfer size
+typedef struct {
+  float latitude;
+  float longitude;
+  float value;
+  uint8_t discipline;
+  uint8_t parameter_category;
+  uint8_t parameter_number;
+  int64_t forecast_time;
+  int64_t reference_time;
+  uint8_t surface_type;
+  float surface_value;
+  uint8_t surface_type_2;
+  float surface_value_2;
+  uint32_t message_index;
+  uint32_t submessage_index;
+  bool is_missing;
+  int32_t ensemble_member;
+  uint8_t statistical_process;
+  int64_t time_range_seconds;
+  uint8_t kind;
+  int32_t probability_type;
+  float threshold_lower;
+  float threshold_upper;
+  int32_t percentile;
+  uint32_t source_index;
+  int64_t valid_time;
+} Grib2DataPointF32;
+
+typedef struct {
+  Grib2DataPointF32 *data;
+  size_t count;
+  bool has_more;
+  char *error;
+} Grib2BatchF32;
+
+// Data point with the integer X its value was packed as,
+// value = (R + X * 2^E) / 10^D; 0 when missing
+typedef struct {
+  Grib2DataPoint point;
+  int64_t raw;
+  bool categorical; // units are a code table (precipitation type, ...)
+} Grib2DataPointRaw;
+
+typedef struct {
+  Grib2DataPointRaw *data;
+  size_t count;
+  bool has_more;
+  char *error;
+} Grib2BatchRaw;
+
+// Data point with its H3 cell (requires the Rust `h3` feature)
+typedef struct {
+  Grib2DataPoint point;
+  uint64_t h3_cell; // 0 when the coordinates aren't finite
+} Grib2DataPointH3;
+
+typedef struct {
+  Grib2DataPointH3 *data;
+  size_t count;
+  bool has_more;
+  char *error;
+} Grib2BatchH3;
+
+// Message metadata, listed without decoding values
+typedef struct {
+  uint8_t discipline;
+  uint8_t parameter_category;
+  uint8_t parameter_number;
+  int64_t forecast_time;
+  int64_t reference_time;
+  uint8_t surface_type;
+  double surface_value;
+  uint8_t surface_type_2;
+  double surface_value_2;
+  uint32_t message_index;
+  uint32_t submessage_index;
+  size_t point_count;
+  int32_t ensemble_member; // -1 if not an ensemble member
+  int32_t ensemble_size;   // forecasts in the ensemble, -1 if not given
+  uint8_t statistical_process; // code table 4.10, 255 if instantaneous
+  int64_t time_range_seconds;  // -1 when given in months or years
+  uint8_t kind;                // Grib2MessageKind
+  int32_t probability_type; // code table 4.9, -1 if not a probability product
+  double threshold_lower;   // NaN when missing or not a probability product
+  double threshold_upper;
+  int32_t percentile; // 0-100 for percentile products, -1 otherwise
+  uint32_t source_index; // file within grib2_open_multi, 0 otherwise
+  int64_t valid_time; // unix seconds, end of interval if statistical; 0 unknown
+} Grib2MessageInfo;
+
+// Identification section (section 1) of the GRIB message holding a field
+typedef struct {
+  uint16_t centre; // code table C-11, 65535 when missing
+  uint16_t subcentre;
+  uint16_t year; // reference time components as coded, UTC
+  uint8_t month;
+  uint8_t day;
+  uint8_t hour;
+  uint8_t minute;
+  uint8_t second;
+  uint8_t reference_time_significance; // code table 1.2
+  uint8_t master_table_version;        // code table 1.0, 255 when missing
+  uint8_t local_table_version;         // code table 1.1, 0 without local tables
+  uint8_t production_status;           // code table 1.3
+  uint8_t data_type;                   // code table 1.4
+} Grib2Identification;
+
+// Why a message produced no points, see grib2_get_skipped
+typedef enum {
+  GRIB2_SKIP_UNSUPPORTED_GRID = 0, // dropped at open
+  GRIB2_SKIP_NO_COORDINATES = 1,
+  GRIB2_SKIP_UNSUPPORTED_PACKING = 2, // e.g. JPEG2000/PNG without the Rust feature
+  GRIB2_SKIP_DECODE_FAILED = 3,
+  GRIB2_SKIP_OPEN_FAILED = 4, // grib2_open_multi left the whole file out
+} Grib2SkipReason;
+
+typedef struct {
+  uint32_t message_index;
+  uint32_t submessage_index;
+  Grib2SkipReason reason;
+  char *error;
+  uint32_t source_index; // file within grib2_open_multi, 0 otherwise
+} Grib2SkippedMessage;
+
+// Result of point queries
+typedef enum {
+  GRIB2_QUERY_OK = 0,
+  GRIB2_QUERY_INVALID_MESSAGE = 1,
+  GRIB2_QUERY_OUTSIDE_GRID = 2,
+  GRIB2_QUERY_UNSUPPORTED_GRID = 3,
+  GRIB2_QUERY_DECODE_FAILED = 4,
+  GRIB2_QUERY_NEAREST_FALLBACK = 5,
+} Grib2QueryStatus;
+
+// How grib2_regrid fills target nodes
+typedef enum {
+  GRIB2_REGRID_NEAREST = 0,
+  GRIB2_REGRID_INVERSE_DISTANCE = 1, // 1/d^2 weights within the search radius
+} Grib2RegridMethod;
+
+// Range of emitted longitudes, see grib2_set_lon_convention
+typedef enum {
+  GRIB2_LON_SIGNED = 0,   // [-180, 180), the default
+  GRIB2_LON_UNSIGNED = 1, // [0, 360), as stored in GRIB2
+} Grib2LonConvention;
+
+// Which messages stream when several hold the same field, see
+// grib2_set_dup_policy
+typedef enum {
+  GRIB2_DUP_KEEP_ALL = 0, // the default
+  GRIB2_DUP_KEEP_FIRST = 1,
+  GRIB2_DUP_KEEP_LAST = 2,
+} Grib2DupPolicy;
+
+// Category of an open failure; error_code out-params may be NULL
+typedef enum {
+  GRIB2_ERROR_OK = 0,
+  GRIB2_ERROR_INVALID_ARGUMENT = 1,
+  GRIB2_ERROR_FILE_NOT_FOUND = 2,
+  GRIB2_ERROR_IO = 3,
+  GRIB2_ERROR_NOT_GRIB = 4, // also GRIB edition 1 data
+  GRIB2_ERROR_UNSUPPORTED_TEMPLATE = 5,
+  GRIB2_ERROR_DECODE_FAILED = 6,
+  GRIB2_ERROR_OUT_OF_BOUNDS = 7,
+  GRIB2_ERROR_HTTP = 8,
+  GRIB2_ERROR_INTERNAL = 9,
+  GRIB2_ERROR_TRUNCATED = 10, // a message runs past the end of the data
+  GRIB2_ERROR_EMPTY = 11,     // no GRIB messages, e.g. a zero-byte file
+  GRIB2_ERROR_NOT_FOUND = 12, // no message matched grib2_open_first_match
+} Grib2ErrorCode;
+
+// Severity passed to a Grib2LogCallback
+typedef enum {
+  GRIB2_LOG_ERROR = 1,
+  GRIB2_LOG_WARN = 2, // a message was skipped or failed to decode, and why
+  GRIB2_LOG_INFO = 3,
+  GRIB2_LOG_DEBUG = 4, // templates of each message at open
+  GRIB2_LOG_TRACE = 5,
+} Grib2LogLevel;
+
+// unit_mode flags for grib2_read_batch_converted
+#define GRIB2_UNITS_CELSIUS 1 // K -> degC (temperature differences unchanged)
+#define GRIB2_UNITS_HPA 2     // Pa -> hPa
+#define GRIB2_UNITS_FEET 4    // m and gpm -> ft
+
+// Kinds returned by grib2_parameter_value_kind
+#define GRIB2_VALUE_CONTINUOUS 0  // physical quantity
+#define GRIB2_VALUE_CATEGORICAL 1 // code-table entry
+
 // Opaque reader handle
+// A handle may be passed between threads, but calls on the same handle must not
+// run concurrently; open one reader per thread for parallel scans
 typedef struct Grib2Reader Grib2Reader;
 
 // Streaming API - file path
 Grib2Reader *grib2_open(const char *path);
-Grib2Reader *grib2_open_with_error(const char *path, char **error);
+Grib2Reader *grib2_open_with_error(const char *path, char **error,
+                                   Grib2ErrorCode *error_code);
+
+// Fail with "message N: ..." instead of skipping messages with unsupported
+// grids or packing, or corrupt data; reads every message's data once
+Grib2Reader *grib2_open_strict(const char *path, char **error,
+                               Grib2ErrorCode *error_code);
+
+// Open several files as one stream of messages in path order, tagged with
+// source_index; a file that fails to open aborts, or with skip_failed is listed
+// by grib2_get_skipped as GRIB2_SKIP_OPEN_FAILED. Fails when no file opens
+Grib2Reader *grib2_open_multi(const char *const *paths, size_t count,
+                              bool skip_failed, char **error,
+                              Grib2ErrorCode *error_code);
+
+// Decode all messages up front on a thread pool (requires the Rust
+// `parallel` feature)
+Grib2Reader *grib2_open_parallel(const char *path, char **error,
+                                 Grib2ErrorCode *error_code);
+
+// Streams reader_a's messages, then reader_b's (source_index shifted past
+// reader_a's). Consumes both inputs: close only the result. NULL, consuming
+// neither, when an input is null or both are the same reader
+Grib2Reader *grib2_merge(Grib2Reader *reader_a, Grib2Reader *reader_b);
+
+// Read a local file through a memory map (requires the Rust `mmap` feature)
+Grib2Reader *grib2_open_mmap(const char *path, char **error,
+                             Grib2ErrorCode *error_code);
+
+// Only decode messages matching the parameter; pass -1 to match any value
+// (products without a category or number, e.g. 4.254 text, only match -1)
+Grib2Reader *grib2_open_with_filter(const char *path, int discipline,
+                                    int param_category, int param_number,
+                                    char **error, Grib2ErrorCode *error_code);
+
+// Only the first field matching the parameter (-1 matches any value); reads no
+// further into the file than the message holding it. NOT_FOUND when none matches;
+// a matching message that can't be read fails with its own error
+Grib2Reader *grib2_open_first_match(const char *path, int discipline,
+                                    int param_category, int param_number,
+                                    char **error, Grib2ErrorCode *error_code);
+
+// Only keep points inside the box; min_lon > max_lon wraps the antimeridian
+Grib2Reader *grib2_open_with_bbox(const char *path, double min_lat,
+                                  double min_lon, double max_lat,
+                                  double max_lon, char **error,
+                                  Grib2ErrorCode *error_code);
+// Keep only messages with a forecast time (seconds) in [min, max]
+Grib2Reader *grib2_open_with_time_filter(const char *path,
+                                         int64_t min_forecast_sec,
+                                         int64_t max_forecast_sec, char **error,
+                                         Grib2ErrorCode *error_code);
+// Keep only messages whose reference + forecast time (unix seconds) is in
+// [min, max]
+Grib2Reader *grib2_open_with_valid_time_filter(const char *path,
+                                               int64_t min_valid_time,
+                                               int64_t max_valid_time,
+                                               char **error,
+                                               Grib2ErrorCode *error_code);
+// Keep only messages whose first fixed surface is surface_type (code table 4.5)
+// within tolerance of one of the values, in surface_value units (Pa for
+// isobaric levels); the others are dropped before decoding
+Grib2Reader *grib2_open_with_level_filter(const char *path,
+                                          uint8_t surface_type,
+                                          const double *values,
+                                          size_t value_count, double tolerance,
+                                          char **error,
+                                          Grib2ErrorCode *error_code);
 
 // Streaming API - in-memory bytes (for HTTP fetched data)
 Grib2Reader *grib2_open_from_bytes(const uint8_t *data, size_t len,
-                                   char **error);
+                                   char **error, Grib2ErrorCode *error_code);
 
-// Reading and cleanup
+// Like grib2_open_from_bytes but without copying: data must stay valid and
+// unmodified until grib2_close returns for the handle
+Grib2Reader *grib2_open_from_bytes_borrowed(const uint8_t *data, size_t len,
+                                            char **error,
+                                            Grib2ErrorCode *error_code);
+
+// Streaming API - host I/O (e.g. DuckDB's FileSystem). read_fn returns the bytes
+// read (0 at the end), seek_fn the new position with fseek's whence (0 start,
+// 1 current, 2 end); negative returns are errors. Callbacks and user_data must
+// stay valid until grib2_close, as messages are read lazily
+typedef int64_t (*Grib2ReadCallback)(void *user_data, uint8_t *buf, size_t len);
+typedef int64_t (*Grib2SeekCallback)(void *user_data, int64_t offset,
+                                     int whence);
+Grib2Reader *grib2_open_from_callback(Grib2ReadCallback read_fn,
+                                      Grib2SeekCallback seek_fn,
+                                      void *user_data, char **error,
+                                      Grib2ErrorCode *error_code);
+
+// Streaming API - HTTP(S) range request (requires the Rust `remote` feature)
+// byte_length 0 reads to the end; HTTP failures start with "HTTP error:"
+Grib2Reader *grib2_open_from_url(const char *url, uint64_t byte_offset,
+                                 uint64_t byte_length, char **error,
+                                 Grib2ErrorCode *error_code);
+
+// Reading and cleanup; hot loops should prefer grib2_read_batch_into
 Grib2Batch grib2_read_batch(Grib2Reader *reader, size_t max_count);
+// Fill a caller-owned buffer of capacity points, reusable across calls with
+// nothing to free; out_count/out_has_more as in Grib2Batch
+bool grib2_read_batch_into(Grib2Reader *reader, Grib2DataPoint *buffer,
+                           size_t capacity, size_t *out_count,
+                           bool *out_has_more);
+// Same points as the next grib2_read_batch without advancing the cursor; free
+// with grib2_free_batch
+Grib2Batch grib2_peek_batch(Grib2Reader *reader, size_t max_count);
+// Push points from the cursor into callback until it returns false or the data
+// ends; returns the number delivered. The point is only valid during the call,
+// and the callback must not throw. Stops early at the point budget, like the
+// read functions
+typedef bool (*Grib2PointCallback)(const Grib2DataPoint *point, void *user_data);
+size_t grib2_for_each_point(Grib2Reader *reader, Grib2PointCallback callback,
+                            void *user_data);
+// Rewind and write every point to a CSV file with a header row (ISO 8601
+// reference times, empty fields for missing values); error is freed with
+// grib2_free_error. Fails, leaving a partial file, when the point budget is
+// reached before the last point
+bool grib2_write_csv(Grib2Reader *reader, const char *path, char **error);
+// Write one regular lat/lon message as a north-up float32 GeoTIFF on WGS 84
+// coordinates, NaN for missing (requires the Rust `geotiff` feature); error is
+// freed with grib2_free_error
+bool grib2_write_geotiff(Grib2Reader *reader, size_t message_index,
+                         const char *path, char **error);
+// Skip bitmap-masked points instead of emitting NaN (default false)
+void grib2_set_skip_missing(Grib2Reader *reader, bool skip);
+// Drop the last column of global lat/lon grids that repeat the first one 360°
+// further on, e.g. both 0° and 360° (default false)
+void grib2_set_dedup_wraparound(Grib2Reader *reader, bool enabled);
+// Report longitudes in [-180, 180) or [0, 360); bboxes stay in [-180, 180)
+void grib2_set_lon_convention(Grib2Reader *reader, Grib2LonConvention mode);
+// Stream only the first or last (in file order) of messages holding the same
+// field: parameter, levels, times, member and processing. Skipped ones keep
+// their positions for the message getters
+void grib2_set_dup_policy(Grib2Reader *reader, Grib2DupPolicy policy);
+// Emit each message north to south, then west to east (default false: scan
+// order). Sorts per message and holds its points in memory while streaming
+void grib2_set_sort_by_coordinate(Grib2Reader *reader, bool sort);
+// Never split a grid row (Ni points, or Nj for column scans) across batches:
+// a batch may hold fewer than max_count points. Keeps scan order, overriding
+// grib2_set_sort_by_coordinate; rows longer than max_count are still split
+void grib2_set_row_aligned(Grib2Reader *reader, bool enabled);
+// Process-wide diagnostics sink (requires the Rust `tracing` feature); NULL
+// stops forwarding. May be called from any reader thread; message is only valid
+// during the call, and the callback must not throw
+typedef void (*Grib2LogCallback)(Grib2LogLevel level, const char *message,
+                                 void *user_data);
+void grib2_set_log_callback(Grib2LogCallback callback, void *user_data);
+// Process-wide LRU cache of unpacked values (bytes, 0 = off and emptied, the
+// default) shared by readers of the same file path, size and mtime
+void grib2_set_cache_capacity(size_t bytes);
+// Rewind to the first point without re-parsing the file
+void grib2_reset(Grib2Reader *reader);
+// Cap the points handed out between resets (0 removes the cap, the default);
+// reading then stops with has_more false. grib2_budget_exceeded tells whether
+// points were left out, i.e. the result is partial
+void grib2_set_point_budget(Grib2Reader *reader, size_t max_total_points);
+bool grib2_budget_exceeded(Grib2Reader *reader);
+// Advance n points without returning them (e.g. OFFSET); returns the count
+// skipped, fewer than n only at the end. Unfiltered whole messages are not
+// decoded and count as their header point count
+size_t grib2_skip_points(Grib2Reader *reader, size_t n);
+// Continue streaming at the first point of a message; false if out of range
+bool grib2_seek_message(Grib2Reader *reader, size_t message_index);
+// Stream position as (message, points read from it after filtering); the end
+// is (message count, 0). grib2_seek resumes there, false and unmoved when the
+// position is out of range
+bool grib2_tell(Grib2Reader *reader, size_t *out_message, size_t *out_point);
+bool grib2_seek(Grib2Reader *reader, size_t message, size_t point);
 size_t grib2_total_points(Grib2Reader *reader);
+// grib2_total_points times sizeof(Grib2DataPointF32) or sizeof(Grib2DataPoint);
+// filters may emit fewer
+size_t grib2_estimate_bytes(Grib2Reader *reader, bool single_precision);
+// Points handed out by the read functions since open or grib2_reset, not
+// counting ones skipped or seeked over
+size_t grib2_points_consumed(Grib2Reader *reader);
+// Message count and point total from the section 3 headers of a file, without
+// opening a reader or decoding anything; every field counts, even ones an open
+// would skip
+bool grib2_inspect(const char *path, size_t *out_message_count,
+                   size_t *out_total_points, char **error,
+                   Grib2ErrorCode *error_code);
+
+// Per-message metadata, message_index is 0..grib2_message_count()-1
+size_t grib2_message_count(Grib2Reader *reader);
+// Grid points in one message before any bbox crop; 0 for an unknown index
+size_t grib2_message_point_count(Grib2Reader *reader, size_t message_index);
+bool grib2_list_messages(Grib2Reader *reader, Grib2MessageInfo **out_array,
+                         size_t *out_count);
+void grib2_free_message_list(Grib2MessageInfo *array, size_t count);
+// Messages dropped at open or that failed to decode in the messages streamed so
+// far, with the reason and error text; free with grib2_free_skipped
+bool grib2_get_skipped(Grib2Reader *reader, Grib2SkippedMessage **out_array,
+                       size_t *out_count);
+void grib2_free_skipped(Grib2SkippedMessage *array, size_t count);
+// Min/max/mean/count of non-missing values, cached per message (NaN if count
+// is 0); false for unknown or undecodable messages
+bool grib2_message_stats(Grib2Reader *reader, size_t message_index,
+                         double *out_min, double *out_max, size_t *out_count,
+                         double *out_mean);
+// Append wind speed (0/2/1) and direction (0/2/0) messages computed from each
+// U/V pair at the same level, time and grid; the returned number of pairs sits
+// at indices out_speed_msg.. and out_dir_msg..; unpaired U messages are skipped
+size_t grib2_wind_derive(Grib2Reader *reader, size_t *out_speed_msg,
+                         size_t *out_dir_msg);
+// Fields in the GRIB message holding a message, including ones filtered out
+// or skipped at open; 0 for unknown messages
+size_t grib2_submessage_count(Grib2Reader *reader, size_t message_index);
+// Message for a field by source/message/submessage numbers as in
+// Grib2MessageInfo; false if it was filtered out, skipped or doesn't exist
+bool grib2_find_submessage(Grib2Reader *reader, uint32_t source_index,
+                           uint32_t message_number, uint32_t submessage_index,
+                           size_t *out_message_index);
+int64_t grib2_get_reference_time(Grib2Reader *reader, size_t message_index);
+uint8_t grib2_get_reference_time_significance(Grib2Reader *reader,
+                                              size_t message_index);
+// Section 1 production status (code table 1.3: 0 operational, 1 operational
+// test, 2 research, 3 re-analysis) and type of data (code table 1.4: 0
+// analysis, 1 forecast, 4 perturbed forecast); 255 when missing or unknown
+uint8_t grib2_get_production_status(Grib2Reader *reader, size_t message_index);
+uint8_t grib2_get_data_type(Grib2Reader *reader, size_t message_index);
+// Originating centre (code table C-11: 7 NCEP, 78 DWD, 98 ECMWF) and
+// sub-centre from section 1, 65535 when missing; false for unknown messages
+bool grib2_get_originating_centre(Grib2Reader *reader, size_t message_index,
+                                  uint16_t *out_centre,
+                                  uint16_t *out_subcentre);
+// Whole section 1 in one call, see Grib2Identification; false for unknown
+// messages
+bool grib2_get_identification(Grib2Reader *reader, size_t message_index,
+                              Grib2Identification *out);
+// Centre-defined model identifier from section 4 (e.g. 96 for GFS at NCEP),
+// 255 when missing
+uint8_t grib2_get_generating_process(Grib2Reader *reader, size_t message_index);
+// Data representation template from section 5 (0 simple, 3 complex, 40
+// JPEG2000, 41 PNG) and bits per packed value, 255 when unknown; available even
+// for messages that fail to decode; false for unknown messages
+bool grib2_get_packing_info(Grib2Reader *reader, size_t message_index,
+                            uint16_t *out_template_number,
+                            uint8_t *out_bits_per_value);
+// Section 5 packing parameters: values unpack as (R + X * 2^E) / 10^D from
+// bits-wide X; false for unknown messages and templates without them (5.200)
+bool grib2_get_decode_params(Grib2Reader *reader, size_t message_index,
+                             float *out_reference, int16_t *out_binary_scale,
+                             int16_t *out_decimal_scale, uint8_t *out_bits);
+// Significant bits per value: the packed width (0 for a constant field), 24 or
+// 53 for IEEE floats; 255 for unknown messages and other templates
+uint8_t grib2_get_precision_bits(Grib2Reader *reader, size_t message_index);
+// Whether the message packs 0 bits per value, every unmasked point holding the
+// reference value R / 10^D; false for unknown messages
+bool grib2_has_constant_field(Grib2Reader *reader, size_t message_index);
+// Extent from the first/last grid points of section 3, without decoding;
+// longitudes in the grib2_set_lon_convention range, min_lon > max_lon when the
+// grid wraps. False for unknown messages and rotated or projected grids
+bool grib2_get_message_bounds(Grib2Reader *reader, size_t message_index,
+                              double *out_min_lat, double *out_min_lon,
+                              double *out_max_lat, double *out_max_lon);
+// Byte offset and total length of the GRIB message holding a field, counted in
+// the data as read (decompressed for .gz/.bz2, within the range of a ranged URL)
+// False for unknown and derived wind messages
+bool grib2_get_message_extent(Grib2Reader *reader, size_t message_index,
+                              uint64_t *out_offset, uint64_t *out_length);
+bool grib2_get_grid_info(Grib2Reader *reader, size_t message_index,
+                         uint32_t *out_ni, uint32_t *out_nj,
+                         uint8_t *out_scan_mode);
+// Grid definition template (code table 3.1) of one message; files can mix
+// grids. 65535 for unknown messages
+uint16_t grib2_get_grid_template(Grib2Reader *reader, size_t message_index);
+// True when every message has an identical section 3 (or there are fewer than
+// two), so coordinates can be computed once
+bool grib2_file_has_uniform_grid(Grib2Reader *reader);
+// The scan_mode bits of flag table 3.4 that order the grid cells: points running
+// west, rows running north, points following columns, alternate rows reversed
+bool grib2_get_scan_flags(Grib2Reader *reader, size_t message_index,
+                          bool *out_i_negative, bool *out_j_positive,
+                          bool *out_j_consecutive, bool *out_alternating);
+// Value of grid cell (i, j), both counted from the first grid point along the
+// scanning directions of scan_mode; OUTSIDE_GRID past Ni/Nj, UNSUPPORTED_GRID
+// for grids without a rectangular layout
+Grib2QueryStatus grib2_get_value_at(Grib2Reader *reader, size_t message_index,
+                                    uint32_t i, uint32_t j, double *out_value);
+// Abbreviation ("TMP") and name ("Temperature") from code table 4.2; static
+// strings, "unknown" for unmapped triples (returns false then)
+bool grib2_parameter_name(uint8_t discipline, uint8_t category,
+                          uint8_t number, const char **out_abbrev,
+                          const char **out_name);
+// Units of a parameter in WMO notation ("K", "m s-1"); static string, ""
+// for unmapped triples (returns false then)
+bool grib2_parameter_units(uint8_t discipline, uint8_t category,
+                           uint8_t number, const char **out_units);
+// Abbreviation, name and units of a message's parameter as its section 1
+// defines it: local entries (192-254) resolve only for NCEP messages with local
+// tables; "unknown"/"" and false otherwise, false for unknown messages
+bool grib2_get_message_parameter(Grib2Reader *reader, size_t message_index,
+                                 const char **out_abbrev, const char **out_name,
+                                 const char **out_units);
+// Values are code-table entries (precipitation type, categorical rain, ...)
+// rather than quantities; false for unmapped triples
+bool grib2_parameter_is_categorical(uint8_t discipline, uint8_t category,
+                                    uint8_t number);
+// GRIB2_VALUE_* kind of a parameter's values, from its table units, so
+// callers can pick a column type; unmapped triples are continuous
+uint8_t grib2_parameter_value_kind(uint8_t discipline, uint8_t category,
+                                   uint8_t number);
+// Stable key of a triple for hashing and joins: discipline << 16 |
+// category << 8 | number; grib2_parameter_unpack returns false for keys with
+// the top byte set
+uint32_t grib2_parameter_key(uint8_t discipline, uint8_t category,
+                             uint8_t number);
+bool grib2_parameter_unpack(uint32_t key, uint8_t *out_discipline,
+                            uint8_t *out_category, uint8_t *out_number);
+
+// Point queries against a single message
+Grib2QueryStatus grib2_point_query(Grib2Reader *reader, double lat, double lon,
+                                   size_t message_index, double *out_value);
+Grib2QueryStatus grib2_interpolate(Grib2Reader *reader, double lat, double lon,
+                                   size_t message_index, double *out_value);
+
+// Resample a message onto a regular lat/lon grid (rows north to south, NaN
+// where no source point is near); out_batch must be freed with
+// grib2_free_batch even on failure, when it carries the error
+bool grib2_regrid(Grib2Reader *reader, size_t message_index,
+                  double target_lat_step, double target_lon_step,
+                  Grib2RegridMethod method, Grib2Batch *out_batch,
+                  uint32_t *out_ni, uint32_t *out_nj);
+
+// Export one message as an Arrow record batch through the C Data Interface
+// (requires the Rust `arrow` feature); the caller releases out_array and
+// out_schema, error is set on failure and freed with grib2_free_error
+struct ArrowArray;
+struct ArrowSchema;
+bool grib2_to_arrow(Grib2Reader *reader, size_t message_index,
+                    struct ArrowArray *out_array,
+                    struct ArrowSchema *out_schema, char **error);
+
+// Encode a regular lat/lon grid (ni points per row, rows as grib2_regrid
+// returns them) as one GRIB2 message with simple packing; time and levels are
+// taken from the first point. out_bytes is freed with grib2_free_encoded
+bool grib2_encode_regular_latlon(const Grib2DataPoint *points, size_t count,
+                                 uint32_t ni, uint32_t nj, uint8_t discipline,
+                                 uint8_t category, uint8_t number,
+                                 uint8_t **out_bytes, size_t *out_len);
+void grib2_free_encoded(uint8_t *bytes, size_t len);
+
+// Batches stay valid after close and must still be freed; closing a reader with
+// batches that were never freed logs a GRIB2_LOG_WARN event
 void grib2_close(Grib2Reader *reader);
+// Batches from this reader not yet passed to a grib2_free_batch* function
+size_t grib2_outstanding_batches(Grib2Reader *reader);
 void grib2_free_batch(Grib2Batch batch);
+Grib2BatchF32 grib2_read_batch_f32(Grib2Reader *reader, size_t max_count);
+// Like grib2_read_batch, converting values of known parameters per unit_mode
+// (mask of GRIB2_UNITS_*); unmapped parameters keep native units
+Grib2Batch grib2_read_batch_converted(Grib2Reader *reader, size_t max_count,
+                                      uint32_t unit_mode);
+void grib2_free_batch_f32(Grib2BatchF32 batch);
+// Points with their packed integers; packings without linear ones (IEEE floats,
+// 5.61 logarithmic) and derived wind give the value rounded
+Grib2BatchRaw grib2_read_batch_raw(Grib2Reader *reader, size_t max_count);
+void grib2_free_batch_raw(Grib2BatchRaw batch);
+// Points with the H3 cell at resolution 0-15 (requires the Rust `h3` feature)
+Grib2BatchH3 grib2_read_batch_h3(Grib2Reader *reader, size_t max_count,
+                                 uint8_t resolution);
+void grib2_free_batch_h3(Grib2BatchH3 batch);
 void grib2_free_error(char *error);
 
 // Legacy API (reads entire file)
@@ -56,6 +631,10 @@ typedef struct {
 } Grib2ReadResult;
 
 Grib2ReadResult grib2_read_file(const char *path);
+// grib2_read_file for one parameter (-1 matches any) without keeping a reader;
+// false with *error set on failure; free out_result with grib2_free_result
+bool grib2_read_filtered(const char *path, int discipline, int category,
+                         int number, Grib2ReadResult *out_result, char **error);
 void grib2_free_result(Grib2ReadResult result);
 
 #ifdef __cplusplus
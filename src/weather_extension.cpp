@@ -7,6 +7,7 @@
 #include "duckdb/main/connection.hpp"
 #include "duckdb/optimizer/optimizer_extension.hpp"
 #include "gfs_forecast_function.hpp"
+#include "grib2_ffi.h"
 #include "grib_function.hpp"
 #include "met_forecast_function.hpp"
 #include "weather_function.hpp"
@@ -20,6 +21,14 @@ static void WeatherOptimizer(OptimizerExtensionInput &input,
 }
 
 static void LoadInternal(ExtensionLoader &loader) {
+  // The Rust library must lay out structs exactly as grib2_ffi.h declares them
+  if (grib2_abi_version() != GRIB2_ABI_VERSION) {
+    throw InternalException(
+        "weather extension was built against GRIB2 ABI version %d but the "
+        "linked library reports %d",
+        GRIB2_ABI_VERSION, grib2_abi_version());
+  }
+
   auto &db = loader.GetDatabaseInstance();
 
   // Register GRIB2 ENUM types first